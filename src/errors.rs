@@ -19,6 +19,16 @@ pub enum TrackReadError {
     FloatConversion(#[from] ParseFloatError),
     #[error("String parsing error while reading track file: {0}")]
     StringParsing(#[from] ParseLengthPrefixedStringError),
+    #[error(
+        "Unsupported LRB major version {major} while reading track file, supported is {supported}"
+    )]
+    UnsupportedLrbVersion { major: u8, supported: u8 },
+    #[error("Invalid section for mod [{name} v{version}] while reading track file: {reason}")]
+    InvalidLrbSection {
+        name: String,
+        version: u16,
+        reason: String,
+    },
     #[error("Amf0 error while reading track file: {0}")]
     Amf0Deserialization(#[from] Amf0DeserializationError),
     #[error("Other error while reading track file: {message}")]
@@ -36,3 +46,13 @@ pub enum TrackWriteError {
     #[error("Other error while reading track file: {message}")]
     Other { message: String },
 }
+
+#[derive(Error, Debug)]
+pub enum TrackBuildError {
+    #[error("Invalid value for [{name}] while building track: {value}")]
+    InvalidData { name: String, value: String },
+    #[error("Duplicate line ids while building track: {ids:?}")]
+    DuplicateLineIds { ids: Vec<u32> },
+    #[error("Ran out of unused line ids while building track")]
+    LineIdOverflow,
+}
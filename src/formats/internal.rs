@@ -1,15 +1,36 @@
 //! Format that lr_formatter_rs parses into and out of, exposed for usage after reading and before writing
+//!
+//! With the `serde` feature enabled, these types implement `Serialize` and `Deserialize`
+//! following the schema documented in `docs/internal.md`.
+
+mod builder;
+mod ids;
+
+pub use builder::TrackBuilder;
+pub use ids::LineIdAllocator;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Properties from the JSON format that aren't interpreted, kept so they can be written back unchanged
+pub type JsonExtensions = serde_json::Map<String, serde_json::Value>;
 
 /// Physics grid implementation used, with 6.2 being the default
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum GridVersion {
     #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "6.2"))]
     V6_2 = 0,
+    #[cfg_attr(feature = "serde", serde(rename = "6.1"))]
     V6_1 = 1,
+    #[cfg_attr(feature = "serde", serde(rename = "6.0"))]
     V6_0 = 2,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum LineType {
     BLUE = 0,
     RED = 1,
@@ -17,13 +38,93 @@ pub enum LineType {
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Vec2 {
     pub x: f64,
     pub y: f64,
 }
 
+/// Rider that is placed on the track when playback starts
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rider {
+    pub start_position: Vec2,
+    pub start_velocity: Vec2,
+    pub start_angle: Option<f64>,
+    pub remountable: Option<bool>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "serde_json::Map::is_empty")
+    )]
+    pub json_extensions: JsonExtensions,
+}
+
+/// Color with 8-bit red, green, and blue channels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RGBColor {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+/// Effect applied by a time-based trigger while it is active
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum TriggerEvent {
+    /// Zooms the camera towards the target zoom
+    Zoom { target: f32 },
+    /// Fades the background towards the target color
+    BackgroundColor(RGBColor),
+    /// Fades the line color towards the target color
+    LineColor(RGBColor),
+}
+
+/// Trigger that activates between a start and end frame of the track's playback
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Trigger {
+    pub start_frame: u32,
+    pub end_frame: u32,
+    pub event: TriggerEvent,
+}
+
+/// Legacy zoom trigger attached to a simulation line, activated when a rider touches the line
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LineTrigger {
+    pub zoom_target: f32,
+    pub frames: u32,
+}
+
+/// Layer that lines can be assigned to
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Layer {
+    pub id: u32,
+    pub name: String,
+    pub visible: bool,
+    pub editable: bool,
+    /// Id of the folder containing this layer, if it is in one
+    pub folder_id: Option<u32>,
+}
+
+/// Folder that groups consecutive layers together
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LayerFolder {
+    pub id: u32,
+    pub name: String,
+    pub visible: bool,
+    pub editable: bool,
+    /// Number of layers contained in the folder
+    pub size: u32,
+}
+
 /// Base line that all line types derive from
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Line {
     pub id: u32,
     pub x1: f64,
@@ -31,25 +132,81 @@ pub struct Line {
     pub x2: f64,
     pub y2: f64,
     pub line_type: LineType,
+    /// Id of the layer the line belongs to, if the format supports layers
+    pub layer: Option<u32>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "serde_json::Map::is_empty")
+    )]
+    pub json_extensions: JsonExtensions,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SimulationLine {
     pub base_line: Line,
     pub flipped: bool,
     pub left_extension: bool,
     pub right_extension: bool,
     pub multiplier: Option<f64>,
+    pub line_trigger: Option<LineTrigger>,
+    /// Id of the line connected to the start of this line, used by older editors
+    pub previous_line_id: Option<u32>,
+    /// Id of the line connected to the end of this line, used by older editors
+    pub next_line_id: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SceneryLine {
     pub base_line: Line,
     pub width: Option<f64>,
 }
 
+/// Song played alongside the track
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SongInfo {
+    pub name: String,
+    /// Offset into the song in seconds when playback starts
+    pub offset: f64,
+}
+
+/// Physics environment the track is simulated in
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PhysicsSettings {
+    pub x_gravity: f32,
+    pub y_gravity: f32,
+    /// Size of the area around lines where contact points are pulled towards them
+    pub gravity_well_size: f64,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            x_gravity: 0.0,
+            y_gravity: 1.0,
+            gravity_well_size: 10.0,
+        }
+    }
+}
+
+/// LRB mod that could not be interpreted when reading, kept so it can be written back unchanged
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnsupportedLrbMod {
+    pub name: String,
+    pub version: u16,
+    pub flags: u8,
+    /// Raw bytes of the mod's data section, empty if the mod has no extra data
+    pub data: Vec<u8>,
+}
+
 /// Struct for storing track properties in an easily accessible way
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default = "InternalTrackFormat::new"))]
 pub struct InternalTrackFormat {
     pub grid_version: GridVersion,
     pub title: String,
@@ -59,7 +216,31 @@ pub struct InternalTrackFormat {
     pub script: String,
     pub simulation_lines: Vec<SimulationLine>,
     pub scenery_lines: Vec<SceneryLine>,
+    /// Start position of the first rider, kept for formats that only support a single rider
     pub start_position: Vec2,
+    /// Riders of the track, which may be empty if the format only stores `start_position`
+    pub riders: Vec<Rider>,
+    pub triggers: Vec<Trigger>,
+    /// Layers of the track in display order
+    pub layers: Vec<Layer>,
+    pub layer_folders: Vec<LayerFolder>,
+    pub physics: PhysicsSettings,
+    /// Background color, or `None` to use the editor's default
+    pub background_color: Option<RGBColor>,
+    /// Line color, or `None` to use the editor's default
+    pub line_color: Option<RGBColor>,
+    /// Camera zoom when playback starts, or `None` to use the editor's default
+    pub start_zoom: Option<f32>,
+    /// Whether riders start without their initial velocity
+    pub zero_start: bool,
+    /// Whether riders can remount their sled after crashing, used for riders whose
+    /// `remountable` is `None` (a rider's own `remountable` setting takes precedence)
+    pub remount: bool,
+    /// Whether lines apply no friction to riders
+    pub frictionless: bool,
+    pub song_info: Option<SongInfo>,
+    pub unsupported_lrb_mods: Vec<UnsupportedLrbMod>,
+    pub json_extensions: JsonExtensions,
 }
 
 impl InternalTrackFormat {
@@ -71,3 +252,116 @@ impl InternalTrackFormat {
         }
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_to_documented_schema() {
+        let mut track = InternalTrackFormat::new();
+        track.title = "Schema".to_string();
+        track.simulation_lines.push(SimulationLine {
+            base_line: Line {
+                id: 1,
+                x1: 0.0,
+                y1: 0.0,
+                x2: 10.0,
+                y2: 0.0,
+                line_type: LineType::RED,
+                layer: None,
+                json_extensions: JsonExtensions::new(),
+            },
+            flipped: false,
+            left_extension: true,
+            right_extension: false,
+            multiplier: Some(2.0),
+            line_trigger: None,
+            previous_line_id: None,
+            next_line_id: None,
+        });
+        track.riders.push(Rider::default());
+        track.triggers.push(Trigger {
+            start_frame: 0,
+            end_frame: 40,
+            event: TriggerEvent::BackgroundColor(RGBColor {
+                red: 1,
+                green: 2,
+                blue: 3,
+            }),
+        });
+
+        let expected = json!({
+            "grid_version": "6.2",
+            "title": "Schema",
+            "artist": "",
+            "description": "",
+            "duration": 1200,
+            "script": "",
+            "simulation_lines": [
+                {
+                    "base_line": {
+                        "id": 1,
+                        "x1": 0.0,
+                        "y1": 0.0,
+                        "x2": 10.0,
+                        "y2": 0.0,
+                        "line_type": "red",
+                        "layer": null,
+                    },
+                    "flipped": false,
+                    "left_extension": true,
+                    "right_extension": false,
+                    "multiplier": 2.0,
+                    "line_trigger": null,
+                    "previous_line_id": null,
+                    "next_line_id": null,
+                },
+            ],
+            "scenery_lines": [],
+            "start_position": { "x": 0.0, "y": 0.0 },
+            "riders": [
+                {
+                    "start_position": { "x": 0.0, "y": 0.0 },
+                    "start_velocity": { "x": 0.0, "y": 0.0 },
+                    "start_angle": null,
+                    "remountable": null,
+                },
+            ],
+            "triggers": [
+                {
+                    "start_frame": 0,
+                    "end_frame": 40,
+                    "event": { "type": "background_color", "red": 1, "green": 2, "blue": 3 },
+                },
+            ],
+            "layers": [],
+            "layer_folders": [],
+            "physics": {
+                "x_gravity": 0.0,
+                "y_gravity": 1.0,
+                "gravity_well_size": 10.0,
+            },
+            "background_color": null,
+            "line_color": null,
+            "start_zoom": null,
+            "zero_start": false,
+            "remount": false,
+            "frictionless": false,
+            "song_info": null,
+            "unsupported_lrb_mods": [],
+            "json_extensions": {},
+        });
+
+        assert_eq!(serde_json::to_value(&track).unwrap(), expected);
+    }
+
+    #[test]
+    fn missing_fields_use_recommended_defaults() {
+        let track: InternalTrackFormat = serde_json::from_value(json!({ "title": "Old" })).unwrap();
+
+        assert_eq!(track.title, "Old");
+        assert_eq!(track.duration, 1200);
+    }
+}
@@ -0,0 +1,374 @@
+use super::{
+    GridVersion, InternalTrackFormat, JsonExtensions, Line, LineIdAllocator, LineType, Rider,
+    SceneryLine, SimulationLine, Vec2,
+};
+use crate::TrackBuildError;
+
+/// Builds an `InternalTrackFormat` programmatically
+///
+/// Lines added from points are given ids when the track is built, after the highest id of the
+/// fully specified lines, and are placed after them in the track.
+///
+/// ```
+/// use lr_formatter_rs::internal::{TrackBuilder, Vec2};
+///
+/// let track = TrackBuilder::new()
+///     .title("Generated")
+///     .start_position(Vec2 { x: 0.0, y: -10.0 })
+///     .blue_line(Vec2 { x: -20.0, y: 0.0 }, Vec2 { x: 20.0, y: 10.0 })
+///     .green_line(Vec2 { x: -20.0, y: 20.0 }, Vec2 { x: 20.0, y: 20.0 })
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(track.simulation_lines.len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrackBuilder {
+    track: InternalTrackFormat,
+    // Lines added from points, in the order they were added, which still need an id
+    unnumbered_lines: Vec<UnnumberedLine>,
+}
+
+#[derive(Debug, Clone)]
+enum UnnumberedLine {
+    Simulation(SimulationLine),
+    Scenery(SceneryLine),
+}
+
+impl Default for TrackBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrackBuilder {
+    /// Creates a builder for an empty track with recommended defaults
+    pub fn new() -> Self {
+        Self {
+            track: InternalTrackFormat::new(),
+            unnumbered_lines: Vec::new(),
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.track.title = title.into();
+        self
+    }
+
+    pub fn artist(mut self, artist: impl Into<String>) -> Self {
+        self.track.artist = artist.into();
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.track.description = description.into();
+        self
+    }
+
+    pub fn duration(mut self, duration: u32) -> Self {
+        self.track.duration = duration;
+        self
+    }
+
+    pub fn script(mut self, script: impl Into<String>) -> Self {
+        self.track.script = script.into();
+        self
+    }
+
+    pub fn grid_version(mut self, grid_version: GridVersion) -> Self {
+        self.track.grid_version = grid_version;
+        self
+    }
+
+    /// Sets the start position, moving the first rider there if one was added
+    pub fn start_position(mut self, start_position: Vec2) -> Self {
+        if let Some(rider) = self.track.riders.first_mut() {
+            rider.start_position = start_position.clone();
+        }
+
+        self.track.start_position = start_position;
+        self
+    }
+
+    /// Adds a rider, where the first rider added also sets the start position
+    pub fn rider(mut self, rider: Rider) -> Self {
+        if self.track.riders.is_empty() {
+            self.track.start_position = rider.start_position.clone();
+        }
+
+        self.track.riders.push(rider);
+        self
+    }
+
+    pub fn blue_line(self, start: Vec2, end: Vec2) -> Self {
+        self.push_simulation_line(LineType::BLUE, start, end)
+    }
+
+    pub fn red_line(self, start: Vec2, end: Vec2) -> Self {
+        self.push_simulation_line(LineType::RED, start, end)
+    }
+
+    pub fn green_line(mut self, start: Vec2, end: Vec2) -> Self {
+        self.unnumbered_lines
+            .push(UnnumberedLine::Scenery(SceneryLine {
+                base_line: new_line(LineType::GREEN, start, end),
+                width: None,
+            }));
+        self
+    }
+
+    /// Adds a fully specified simulation line, keeping its id so its line links stay valid
+    pub fn simulation_line(mut self, line: SimulationLine) -> Self {
+        self.track.simulation_lines.push(line);
+        self
+    }
+
+    /// Adds a fully specified scenery line, keeping its id
+    pub fn scenery_line(mut self, line: SceneryLine) -> Self {
+        self.track.scenery_lines.push(line);
+        self
+    }
+
+    /// Assigns ids to the lines added from points, then validates the track and returns it
+    pub fn build(self) -> Result<InternalTrackFormat, TrackBuildError> {
+        let mut track = self.track;
+        let mut line_ids = LineIdAllocator::new(&track);
+
+        for line in self.unnumbered_lines {
+            match line {
+                UnnumberedLine::Simulation(mut line) => {
+                    line.base_line.id = line_ids.allocate()?;
+                    track.simulation_lines.push(line);
+                }
+                UnnumberedLine::Scenery(mut line) => {
+                    line.base_line.id = line_ids.allocate()?;
+                    track.scenery_lines.push(line);
+                }
+            }
+        }
+
+        let start_position = &track.start_position;
+        if !start_position.x.is_finite() || !start_position.y.is_finite() {
+            return Err(TrackBuildError::InvalidData {
+                name: "start position".to_string(),
+                value: format!("{:?}", start_position),
+            });
+        }
+
+        let lines = track
+            .simulation_lines
+            .iter()
+            .map(|line| &line.base_line)
+            .chain(track.scenery_lines.iter().map(|line| &line.base_line));
+
+        for line in lines {
+            let coordinates = [line.x1, line.y1, line.x2, line.y2];
+            if coordinates.iter().any(|coordinate| !coordinate.is_finite()) {
+                return Err(TrackBuildError::InvalidData {
+                    name: format!("line {} coordinates", line.id),
+                    value: format!("{:?}", coordinates),
+                });
+            }
+        }
+
+        for line in &track.scenery_lines {
+            if line.base_line.line_type != LineType::GREEN {
+                return Err(TrackBuildError::InvalidData {
+                    name: format!("scenery line {} type", line.base_line.id),
+                    value: format!("{:?}", line.base_line.line_type),
+                });
+            }
+        }
+
+        for line in &track.simulation_lines {
+            let base_line = &line.base_line;
+            if base_line.line_type == LineType::GREEN {
+                return Err(TrackBuildError::InvalidData {
+                    name: format!("simulation line {} type", base_line.id),
+                    value: format!("{:?}", base_line.line_type),
+                });
+            }
+
+            // Zero length lines have no direction, which breaks collision physics
+            if base_line.x1 == base_line.x2 && base_line.y1 == base_line.y2 {
+                return Err(TrackBuildError::InvalidData {
+                    name: format!("line {} length", base_line.id),
+                    value: "0".to_string(),
+                });
+            }
+        }
+
+        let duplicate_ids = track.duplicate_line_ids();
+        if !duplicate_ids.is_empty() {
+            return Err(TrackBuildError::DuplicateLineIds { ids: duplicate_ids });
+        }
+
+        Ok(track)
+    }
+
+    fn push_simulation_line(mut self, line_type: LineType, start: Vec2, end: Vec2) -> Self {
+        self.unnumbered_lines
+            .push(UnnumberedLine::Simulation(SimulationLine {
+                base_line: new_line(line_type, start, end),
+                flipped: false,
+                left_extension: false,
+                right_extension: false,
+                multiplier: None,
+                line_trigger: None,
+                previous_line_id: None,
+                next_line_id: None,
+            }));
+        self
+    }
+}
+
+/// Creates a line between two points, with an id that is assigned when building
+fn new_line(line_type: LineType, start: Vec2, end: Vec2) -> Line {
+    Line {
+        id: 0,
+        x1: start.x,
+        y1: start.y,
+        x2: end.x,
+        y2: end.y,
+        line_type,
+        layer: None,
+        json_extensions: JsonExtensions::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    fn base_line(id: u32, line_type: LineType) -> Line {
+        Line {
+            id,
+            ..new_line(line_type, point(0.0, 0.0), point(10.0, 0.0))
+        }
+    }
+
+    fn simulation_line(id: u32, previous: Option<u32>, next: Option<u32>) -> SimulationLine {
+        SimulationLine {
+            base_line: base_line(id, LineType::BLUE),
+            flipped: false,
+            left_extension: previous.is_some(),
+            right_extension: next.is_some(),
+            multiplier: None,
+            line_trigger: None,
+            previous_line_id: previous,
+            next_line_id: next,
+        }
+    }
+
+    #[test]
+    fn numbers_lines_after_specified_lines() {
+        let track = TrackBuilder::new()
+            .blue_line(point(0.0, 0.0), point(10.0, 0.0))
+            .simulation_line(simulation_line(5, None, Some(0)))
+            .simulation_line(simulation_line(0, Some(5), None))
+            .green_line(point(0.0, 5.0), point(10.0, 5.0))
+            .build()
+            .unwrap();
+
+        let simulation_ids: Vec<u32> = track
+            .simulation_lines
+            .iter()
+            .map(|line| line.base_line.id)
+            .collect();
+        assert_eq!(simulation_ids, vec![5, 0, 6]);
+        assert_eq!(track.scenery_lines[0].base_line.id, 7);
+        assert_eq!(track.simulation_lines[0].next_line_id, Some(0));
+        assert_eq!(track.simulation_lines[1].previous_line_id, Some(5));
+    }
+
+    #[test]
+    fn start_position_moves_the_first_rider() {
+        let track = TrackBuilder::new()
+            .rider(Rider {
+                start_position: point(1.0, 2.0),
+                ..Rider::default()
+            })
+            .start_position(point(3.0, 4.0))
+            .build()
+            .unwrap();
+
+        assert_eq!(track.start_position.x, 3.0);
+        assert_eq!(track.riders[0].start_position.x, 3.0);
+        assert_eq!(track.riders[0].start_position.y, 4.0);
+    }
+
+    #[test]
+    fn rejects_non_finite_coordinates() {
+        let line = TrackBuilder::new()
+            .red_line(point(0.0, 0.0), point(f64::NAN, 0.0))
+            .build();
+        let start = TrackBuilder::new()
+            .start_position(point(f64::INFINITY, 0.0))
+            .build();
+
+        assert!(matches!(line, Err(TrackBuildError::InvalidData { .. })));
+        assert!(matches!(start, Err(TrackBuildError::InvalidData { .. })));
+    }
+
+    #[test]
+    fn rejects_zero_length_simulation_lines() {
+        let result = TrackBuilder::new()
+            .blue_line(point(1.0, 1.0), point(1.0, 1.0))
+            .build();
+
+        assert!(matches!(result, Err(TrackBuildError::InvalidData { .. })));
+    }
+
+    #[test]
+    fn rejects_lines_of_the_wrong_type() {
+        let green_simulation_line = SimulationLine {
+            base_line: base_line(0, LineType::GREEN),
+            ..simulation_line(0, None, None)
+        };
+        let blue_scenery_line = SceneryLine {
+            base_line: base_line(0, LineType::BLUE),
+            width: None,
+        };
+
+        assert!(matches!(
+            TrackBuilder::new()
+                .simulation_line(green_simulation_line)
+                .build(),
+            Err(TrackBuildError::InvalidData { .. })
+        ));
+        assert!(matches!(
+            TrackBuilder::new().scenery_line(blue_scenery_line).build(),
+            Err(TrackBuildError::InvalidData { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_duplicate_line_ids() {
+        let result = TrackBuilder::new()
+            .simulation_line(simulation_line(3, None, None))
+            .scenery_line(SceneryLine {
+                base_line: base_line(3, LineType::GREEN),
+                width: None,
+            })
+            .build();
+
+        assert!(matches!(
+            result,
+            Err(TrackBuildError::DuplicateLineIds { ids }) if ids == vec![3]
+        ));
+    }
+
+    #[test]
+    fn running_out_of_line_ids_is_an_error() {
+        let result = TrackBuilder::new()
+            .simulation_line(simulation_line(u32::MAX, None, None))
+            .blue_line(point(0.0, 0.0), point(10.0, 0.0))
+            .build();
+
+        assert!(matches!(result, Err(TrackBuildError::LineIdOverflow)));
+    }
+}
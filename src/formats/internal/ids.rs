@@ -0,0 +1,274 @@
+use super::InternalTrackFormat;
+use crate::TrackBuildError;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Hands out line ids that aren't used by any line of a track
+#[derive(Debug, Clone)]
+pub struct LineIdAllocator {
+    // None once every id above the highest line id has been handed out
+    next_id: Option<u32>,
+}
+
+impl LineIdAllocator {
+    /// Creates an allocator that starts after the highest line id of the track
+    pub fn new(track: &InternalTrackFormat) -> Self {
+        Self {
+            next_id: track.next_line_id(),
+        }
+    }
+
+    /// Creates an allocator that starts after the highest line id of the track, but not below
+    /// `first_id`
+    pub fn starting_at(track: &InternalTrackFormat, first_id: u32) -> Self {
+        Self {
+            next_id: track.next_line_id().map(|id| id.max(first_id)),
+        }
+    }
+
+    /// Returns a new unused line id, or an error if the ids above the highest line id ran out
+    pub fn allocate(&mut self) -> Result<u32, TrackBuildError> {
+        let id = self.next_id.ok_or(TrackBuildError::LineIdOverflow)?;
+        self.next_id = id.checked_add(1);
+        Ok(id)
+    }
+}
+
+impl InternalTrackFormat {
+    /// Returns the id following the highest line id of the track, 0 if it has no lines, or `None`
+    /// if the highest line id is `u32::MAX`
+    pub fn next_line_id(&self) -> Option<u32> {
+        match self.line_ids().max() {
+            Some(max_id) => max_id.checked_add(1),
+            None => Some(0),
+        }
+    }
+
+    /// Returns the ids that are shared by more than one line, in ascending order
+    pub fn duplicate_line_ids(&self) -> Vec<u32> {
+        let mut seen = HashSet::new();
+        let duplicates: BTreeSet<u32> = self.line_ids().filter(|id| !seen.insert(*id)).collect();
+
+        duplicates.into_iter().collect()
+    }
+
+    /// Gives every line that reuses an earlier line's id a new unused id
+    ///
+    /// Simulation lines are checked before scenery lines, so the first simulation line with an id
+    /// keeps it, and line links keep pointing to that line. The track is left unchanged if there
+    /// aren't enough unused ids for every duplicate.
+    pub fn resolve_duplicate_line_ids(&mut self) -> Result<(), TrackBuildError> {
+        let mut allocator = LineIdAllocator::new(self);
+        let mut seen = HashSet::new();
+        let duplicate_count = self.line_ids().filter(|id| !seen.insert(*id)).count();
+        let new_ids = (0..duplicate_count)
+            .map(|_| allocator.allocate())
+            .collect::<Result<Vec<u32>, _>>()?;
+
+        let mut new_ids = new_ids.into_iter();
+        seen.clear();
+
+        let lines = self
+            .simulation_lines
+            .iter_mut()
+            .map(|line| &mut line.base_line)
+            .chain(
+                self.scenery_lines
+                    .iter_mut()
+                    .map(|line| &mut line.base_line),
+            );
+
+        for line in lines {
+            if !seen.insert(line.id)
+                && let Some(new_id) = new_ids.next()
+            {
+                line.id = new_id;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renumbers all lines to consecutive ids starting at `first_id`, keeping their relative order
+    ///
+    /// Line links are remapped to the new ids of the lines they point to. Links to lines that
+    /// don't exist in the track are removed, since they could otherwise point to a different line
+    /// after renumbering. Line triggers and layer assignments are stored on the lines themselves,
+    /// so they stay with their lines. Returns an error without changing the track if the new ids
+    /// wouldn't fit in a `u32`.
+    pub fn renumber_line_ids(&mut self, first_id: u32) -> Result<(), TrackBuildError> {
+        // Links only point to simulation lines, and the first line with a duplicated id wins
+        let mut simulation_id_map: HashMap<u32, u32> = HashMap::new();
+
+        let mut line_order: Vec<(u32, bool, usize)> = self
+            .simulation_lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| (line.base_line.id, false, index))
+            .chain(
+                self.scenery_lines
+                    .iter()
+                    .enumerate()
+                    .map(|(index, line)| (line.base_line.id, true, index)),
+            )
+            .collect();
+        line_order.sort_unstable();
+
+        let available_ids = u64::from(u32::MAX - first_id) + 1;
+        if line_order.len() as u64 > available_ids {
+            return Err(TrackBuildError::LineIdOverflow);
+        }
+
+        for (new_id, (old_id, is_scenery, index)) in (first_id..=u32::MAX).zip(line_order) {
+            if is_scenery {
+                self.scenery_lines[index].base_line.id = new_id;
+            } else {
+                self.simulation_lines[index].base_line.id = new_id;
+                simulation_id_map.entry(old_id).or_insert(new_id);
+            }
+        }
+
+        for line in &mut self.simulation_lines {
+            line.previous_line_id = line
+                .previous_line_id
+                .and_then(|id| simulation_id_map.get(&id).copied());
+            line.next_line_id = line
+                .next_line_id
+                .and_then(|id| simulation_id_map.get(&id).copied());
+        }
+
+        Ok(())
+    }
+
+    fn line_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.simulation_lines
+            .iter()
+            .map(|line| line.base_line.id)
+            .chain(self.scenery_lines.iter().map(|line| line.base_line.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::internal::{JsonExtensions, Line, LineType, SceneryLine, SimulationLine};
+
+    fn base_line(id: u32, line_type: LineType) -> Line {
+        Line {
+            id,
+            x1: 0.0,
+            y1: 0.0,
+            x2: 1.0,
+            y2: 1.0,
+            line_type,
+            layer: None,
+            json_extensions: JsonExtensions::new(),
+        }
+    }
+
+    fn simulation_line(id: u32, previous: Option<u32>, next: Option<u32>) -> SimulationLine {
+        SimulationLine {
+            base_line: base_line(id, LineType::BLUE),
+            flipped: false,
+            left_extension: previous.is_some(),
+            right_extension: next.is_some(),
+            multiplier: None,
+            line_trigger: None,
+            previous_line_id: previous,
+            next_line_id: next,
+        }
+    }
+
+    fn scenery_line(id: u32) -> SceneryLine {
+        SceneryLine {
+            base_line: base_line(id, LineType::GREEN),
+            width: None,
+        }
+    }
+
+    #[test]
+    fn finds_duplicates_across_line_kinds() {
+        let mut track = InternalTrackFormat::new();
+        track.simulation_lines = vec![
+            simulation_line(4, None, None),
+            simulation_line(7, None, None),
+        ];
+        track.scenery_lines = vec![scenery_line(7), scenery_line(2), scenery_line(2)];
+
+        assert_eq!(track.duplicate_line_ids(), vec![2, 7]);
+        assert_eq!(track.next_line_id(), Some(8));
+    }
+
+    #[test]
+    fn allocates_ids_from_the_first_id() {
+        let mut track = InternalTrackFormat::new();
+
+        assert_eq!(LineIdAllocator::new(&track).allocate().unwrap(), 0);
+        assert_eq!(
+            LineIdAllocator::starting_at(&track, 1).allocate().unwrap(),
+            1
+        );
+
+        track.simulation_lines = vec![simulation_line(4, None, None)];
+
+        assert_eq!(
+            LineIdAllocator::starting_at(&track, 1).allocate().unwrap(),
+            5
+        );
+    }
+
+    #[test]
+    fn resolves_duplicates_keeping_simulation_ids() {
+        let mut track = InternalTrackFormat::new();
+        track.simulation_lines = vec![
+            simulation_line(1, None, None),
+            simulation_line(2, Some(1), None),
+        ];
+        track.scenery_lines = vec![scenery_line(1)];
+
+        track.resolve_duplicate_line_ids().unwrap();
+
+        assert!(track.duplicate_line_ids().is_empty());
+        assert_eq!(track.simulation_lines[0].base_line.id, 1);
+        assert_eq!(track.simulation_lines[1].previous_line_id, Some(1));
+        assert_eq!(track.scenery_lines[0].base_line.id, 3);
+    }
+
+    #[test]
+    fn renumbering_remaps_links() {
+        let mut track = InternalTrackFormat::new();
+        track.simulation_lines = vec![
+            simulation_line(50, None, Some(20)),
+            simulation_line(20, Some(50), Some(99)),
+        ];
+        track.scenery_lines = vec![scenery_line(30)];
+
+        track.renumber_line_ids(1).unwrap();
+
+        let simulation_ids: Vec<u32> = track
+            .simulation_lines
+            .iter()
+            .map(|line| line.base_line.id)
+            .collect();
+        assert_eq!(simulation_ids, vec![3, 1]);
+        assert_eq!(track.scenery_lines[0].base_line.id, 2);
+        assert_eq!(track.simulation_lines[0].next_line_id, Some(1));
+        assert_eq!(track.simulation_lines[1].previous_line_id, Some(3));
+        assert_eq!(track.simulation_lines[1].next_line_id, None);
+    }
+
+    #[test]
+    fn running_out_of_ids_is_an_error() {
+        let mut track = InternalTrackFormat::new();
+        track.simulation_lines = vec![simulation_line(u32::MAX, None, None)];
+        track.scenery_lines = vec![scenery_line(u32::MAX)];
+
+        assert!(LineIdAllocator::new(&track).allocate().is_err());
+        assert!(LineIdAllocator::starting_at(&track, 1).allocate().is_err());
+        assert!(track.resolve_duplicate_line_ids().is_err());
+        assert!(track.renumber_line_ids(u32::MAX).is_err());
+        assert_eq!(track.scenery_lines[0].base_line.id, u32::MAX);
+
+        track.renumber_line_ids(u32::MAX - 1).unwrap();
+        assert_eq!(track.scenery_lines[0].base_line.id, u32::MAX);
+    }
+}
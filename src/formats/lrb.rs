@@ -1,47 +1,164 @@
 //! Format proposed to unify feature sets across versions, see [LRBSpec](https://github.com/lrbspec)
+//!
+//! Mods outside of the base set can be supported by registering a [`ModHandler`] for them in a
+//! [`ModRegistry`], then passing it to [`read_with_registry`] and [`write_with_registry`].
+//! [`inspect`] lists the mods of a file without reading their data.
+//!
+//! # Versions
+//!
+//! The version byte of the header holds a major version in its upper four bits and a minor
+//! version in its lower four bits. A new major version may change the header or mod table layout,
+//! so files with any other major version than the one of [`LrbVersion::CURRENT`] fail with
+//! [`TrackReadError::UnsupportedLrbVersion`]. Minor versions keep the layout and only add mods, so
+//! every minor version of the current major version is read with the current layout: mods this
+//! crate doesn't know are kept as unsupported mods, and mods a file doesn't have are left at their
+//! defaults. Files are always written with the current version.
 
 mod base;
 mod common;
+mod inspect;
 mod reader;
+mod registry;
 mod writer;
 
-pub use reader::read;
-pub use writer::write;
+pub use inspect::{LrbInfo, ModFlags, ModInfo, inspect, inspect_with_registry};
+pub use reader::{read, read_version, read_with_registry};
+pub use registry::ModRegistry;
+pub use writer::{write, write_with_options, write_with_registry};
 
 use crate::{TrackReadError, TrackWriteError, formats::internal::InternalTrackFormat};
-use base::{GRIDVER, LABEL, SCNLINE, SIMLINE, STARTOFFSET};
-use once_cell::sync::Lazy;
 use std::{collections::HashMap, io::Cursor};
 
-mod mod_flags {
-    pub(crate) const REQUIRED: u8 = 1 << 0;
-    pub(crate) const PHYSICS: u8 = 1 << 1;
-    pub(crate) const CAMERA: u8 = 1 << 2;
-    pub(crate) const SCENERY: u8 = 1 << 3;
-    pub(crate) const EXTRA_DATA: u8 = 1 << 4;
+/// Version of the LRB container format, stored as a single byte in the header with the major
+/// version in the upper four bits and the minor version in the lower four bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LrbVersion(pub u8);
+
+impl LrbVersion {
+    /// Version written by this crate
+    pub const CURRENT: Self = Self::new(0, 0);
+
+    /// Creates a version from its major and minor parts, which only keep their lower four bits
+    pub const fn new(major: u8, minor: u8) -> Self {
+        Self(((major & 0x0F) << 4) | (minor & 0x0F))
+    }
+
+    pub fn major(self) -> u8 {
+        self.0 >> 4
+    }
+
+    pub fn minor(self) -> u8 {
+        self.0 & 0x0F
+    }
+
+    /// Whether files of this version can be read, which holds for every minor version of the
+    /// current major version
+    pub fn is_supported(self) -> bool {
+        self.major() == Self::CURRENT.major()
+    }
+}
+
+/// How coordinates are stored by the line mods
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEncoding {
+    /// Full precision coordinates, which are always lossless
+    #[default]
+    F64,
+    /// Single precision coordinates, which are lossless only if every coordinate fits in an `f32`
+    F32,
+    /// Fixed point differences to the previous line's coordinates, which are lossless only if
+    /// every coordinate is a multiple of 1/256
+    Delta,
 }
 
-struct ModHandler {
+/// Options for writing LRB tracks
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Compact encodings need version 2 of the line mods, so they're only written when selected
+    pub line_encoding: LineEncoding,
+    /// Leaves out mods whose data section is the same as for a new track, since reading
+    /// them changes nothing. Required mods are always written.
+    pub skip_empty_sections: bool,
+    /// Names of registered mods to mark with [`mod_flags::REQUIRED`]
+    pub required_mods: Vec<String>,
+    /// Versions to write for registered mods instead of their highest version
+    pub pinned_versions: HashMap<String, u16>,
+}
+
+/// Flags describing what a mod affects, stored in the mod table
+pub mod mod_flags {
+    /// Readers must fail if they don't support the mod
+    pub const REQUIRED: u8 = 1 << 0;
+    /// Ignoring the mod may affect track physics
+    pub const PHYSICS: u8 = 1 << 1;
+    /// Ignoring the mod may affect camera functionality
+    pub const CAMERA: u8 = 1 << 2;
+    /// Ignoring the mod may affect scenery rendering
+    pub const SCENERY: u8 = 1 << 3;
+    /// The mod has a data section, located by the offset and length following its flags
+    pub const EXTRA_DATA: u8 = 1 << 4;
+}
+
+type ModReadFn = dyn Fn(&mut Cursor<&[u8]>, &mut InternalTrackFormat) -> Result<(), TrackReadError>
+    + Send
+    + Sync;
+type ModWriteFn = dyn Fn(&mut Cursor<Vec<u8>>, &InternalTrackFormat, &WriteOptions) -> Result<(), TrackWriteError>
+    + Send
+    + Sync;
+type ModConditionFn = dyn Fn(&InternalTrackFormat, &WriteOptions) -> bool + Send + Sync;
+
+/// Reads and writes the data section of a single mod version
+pub struct ModHandler {
     flags: u8,
-    read: Box<
-        dyn Fn(&mut Cursor<&[u8]>, &mut InternalTrackFormat) -> Result<(), TrackReadError>
-            + Send
-            + Sync,
-    >,
-    write: Box<
-        dyn Fn(&mut Cursor<Vec<u8>>, &InternalTrackFormat) -> Result<(), TrackWriteError>
-            + Send
-            + Sync,
-    >,
+    read: Box<ModReadFn>,
+    write: Box<ModWriteFn>,
+    write_condition: Option<Box<ModConditionFn>>,
 }
 
-static SUPPORTED_MODS: Lazy<HashMap<(&'static str, u16), &'static Lazy<ModHandler>>> =
-    Lazy::new(|| {
-        HashMap::from([
-            (("base.gridver", 0), &GRIDVER),
-            (("base.label", 0), &LABEL),
-            (("base.scnline", 0), &SCNLINE),
-            (("base.simline", 0), &SIMLINE),
-            (("base.startoffset", 0), &STARTOFFSET),
-        ])
-    });
+impl ModHandler {
+    /// Creates a handler from its mod table flags and functions for reading and writing its data section
+    ///
+    /// The read function is given a cursor over only the mod's data section, and must read all of
+    /// it. The write function appends the data section to the end of the cursor, following the
+    /// options the track is written with where they apply to the mod. Neither is called
+    /// if `flags` doesn't contain [`mod_flags::EXTRA_DATA`].
+    pub fn new(
+        flags: u8,
+        read: impl Fn(&mut Cursor<&[u8]>, &mut InternalTrackFormat) -> Result<(), TrackReadError>
+        + Send
+        + Sync
+        + 'static,
+        write: impl Fn(
+            &mut Cursor<Vec<u8>>,
+            &InternalTrackFormat,
+            &WriteOptions,
+        ) -> Result<(), TrackWriteError>
+        + Send
+        + Sync
+        + 'static,
+    ) -> Self {
+        Self {
+            flags,
+            read: Box::new(read),
+            write: Box::new(write),
+            write_condition: None,
+        }
+    }
+
+    /// Only writes this version when `condition` holds for the track and options, writing the
+    /// next lower version of the mod otherwise
+    ///
+    /// This lets a newer version that adds features be written only for tracks that use them, so
+    /// other tracks stay readable by readers that only know the older version.
+    pub fn write_when(
+        mut self,
+        condition: impl Fn(&InternalTrackFormat, &WriteOptions) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.write_condition = Some(Box::new(condition));
+        self
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+}
@@ -1,11 +1,33 @@
+mod colors;
 mod gridver;
 mod label;
+mod layers;
+mod linelayers;
+mod linelinks;
+mod linetriggers;
+mod metadata;
+mod physics;
+mod riders;
 mod scnline;
 mod simline;
+mod song;
 mod startoffset;
+mod startzoom;
+mod triggers;
 
-pub(super) use gridver::GRIDVER;
-pub(super) use label::LABEL;
-pub(super) use scnline::SCNLINE;
-pub(super) use simline::SIMLINE;
-pub(super) use startoffset::STARTOFFSET;
+pub(super) use colors::colors;
+pub(super) use gridver::gridver;
+pub(super) use label::label;
+pub(super) use layers::layers;
+pub(super) use linelayers::linelayers;
+pub(super) use linelinks::linelinks;
+pub(super) use linetriggers::linetriggers;
+pub(super) use metadata::metadata;
+pub(super) use physics::physics;
+pub(super) use riders::riders;
+pub(super) use scnline::scnline;
+pub(super) use simline::simline;
+pub(super) use song::song;
+pub(super) use startoffset::startoffset;
+pub(super) use startzoom::startzoom;
+pub(super) use triggers::triggers;
@@ -0,0 +1,54 @@
+use crate::formats::lrb::{
+    ModHandler,
+    common::{read_color, write_color},
+    mod_flags,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+// flags: u8 = Color flags 000000BA
+// background: u8[3] = the red, green, and blue channels of the background color (only if A is set)
+// line: u8[3] = the red, green, and blue channels of the line color (only if B is set)
+// Color flag defs: A = has background color, B = has line color
+
+mod color_flags {
+    pub const BACKGROUND: u8 = 1 << 0;
+    pub const LINE: u8 = 1 << 1;
+}
+
+pub(in crate::formats::lrb) fn colors() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA | mod_flags::SCENERY,
+        read: Box::new(|cursor, output| {
+            let flags = cursor.read_u8()?;
+
+            if flags & color_flags::BACKGROUND != 0 {
+                output.background_color = Some(read_color(cursor)?);
+            }
+            if flags & color_flags::LINE != 0 {
+                output.line_color = Some(read_color(cursor)?);
+            }
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            let mut flags: u8 = 0;
+            if internal.background_color.is_some() {
+                flags |= color_flags::BACKGROUND;
+            }
+            if internal.line_color.is_some() {
+                flags |= color_flags::LINE;
+            }
+            buffer.write_u8(flags)?;
+
+            if let Some(background_color) = internal.background_color {
+                write_color(buffer, background_color)?;
+            }
+            if let Some(line_color) = internal.line_color {
+                write_color(buffer, line_color)?;
+            }
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
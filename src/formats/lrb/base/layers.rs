@@ -0,0 +1,114 @@
+use crate::{
+    formats::{
+        internal::{Layer, LayerFolder},
+        lrb::{ModHandler, common::write_string_u16, mod_flags},
+    },
+    util::{StringLength, parse_string},
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// layer count: u32 = the amount of layers written
+// layers: layer[layer count] = [
+//   id: u32 = the layer's ID
+//   name: u16 length string = the layer's name
+//   flags: u8 = Layer flags 00000CBA
+//   folder id: u32 = the ID of the folder containing the layer (only if C is set)
+// ]
+// folder count: u32 = the amount of layer folders written
+// folders: folder[folder count] = [
+//   id: u32 = the folder's ID
+//   name: u16 length string = the folder's name
+//   flags: u8 = Layer flags 000000BA
+//   size: u32 = the amount of layers in the folder
+// ]
+// Layer flag defs: A = visible, B = editable, C = in folder
+// Layers are written in display order
+
+mod layer_flags {
+    pub const VISIBLE: u8 = 1 << 0;
+    pub const EDITABLE: u8 = 1 << 1;
+    pub const IN_FOLDER: u8 = 1 << 2;
+}
+
+pub(in crate::formats::lrb) fn layers() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA | mod_flags::SCENERY,
+        read: Box::new(|cursor, output| {
+            let num_layers = cursor.read_u32::<LittleEndian>()?;
+            for _ in 0..num_layers {
+                let id = cursor.read_u32::<LittleEndian>()?;
+                let name = parse_string::<LittleEndian>(cursor, StringLength::U16)?;
+                let flags = cursor.read_u8()?;
+                let folder_id = if flags & layer_flags::IN_FOLDER != 0 {
+                    Some(cursor.read_u32::<LittleEndian>()?)
+                } else {
+                    None
+                };
+
+                output.layers.push(Layer {
+                    id,
+                    name,
+                    visible: flags & layer_flags::VISIBLE != 0,
+                    editable: flags & layer_flags::EDITABLE != 0,
+                    folder_id,
+                });
+            }
+
+            let num_folders = cursor.read_u32::<LittleEndian>()?;
+            for _ in 0..num_folders {
+                let id = cursor.read_u32::<LittleEndian>()?;
+                let name = parse_string::<LittleEndian>(cursor, StringLength::U16)?;
+                let flags = cursor.read_u8()?;
+                let size = cursor.read_u32::<LittleEndian>()?;
+
+                output.layer_folders.push(LayerFolder {
+                    id,
+                    name,
+                    visible: flags & layer_flags::VISIBLE != 0,
+                    editable: flags & layer_flags::EDITABLE != 0,
+                    size,
+                });
+            }
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            buffer.write_u32::<LittleEndian>(internal.layers.len().try_into()?)?;
+            for layer in &internal.layers {
+                let mut flags = visibility_flags(layer.visible, layer.editable);
+                if layer.folder_id.is_some() {
+                    flags |= layer_flags::IN_FOLDER;
+                }
+
+                buffer.write_u32::<LittleEndian>(layer.id)?;
+                write_string_u16(buffer, &layer.name)?;
+                buffer.write_u8(flags)?;
+                if let Some(folder_id) = layer.folder_id {
+                    buffer.write_u32::<LittleEndian>(folder_id)?;
+                }
+            }
+
+            buffer.write_u32::<LittleEndian>(internal.layer_folders.len().try_into()?)?;
+            for folder in &internal.layer_folders {
+                buffer.write_u32::<LittleEndian>(folder.id)?;
+                write_string_u16(buffer, &folder.name)?;
+                buffer.write_u8(visibility_flags(folder.visible, folder.editable))?;
+                buffer.write_u32::<LittleEndian>(folder.size)?;
+            }
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
+
+fn visibility_flags(visible: bool, editable: bool) -> u8 {
+    let mut flags: u8 = 0;
+    if visible {
+        flags |= layer_flags::VISIBLE;
+    }
+    if editable {
+        flags |= layer_flags::EDITABLE;
+    }
+    flags
+}
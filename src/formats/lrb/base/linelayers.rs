@@ -0,0 +1,76 @@
+use crate::{
+    TrackReadError,
+    formats::lrb::{ModHandler, common::base_lines_mut, mod_flags},
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// count: u32 = the amount of lines written, either 0 if no line has a layer, or the amount of
+//   simulation lines followed by scenery lines in the track
+// lines: linelayer[count] = [
+//   flags: u8 = Layer flags 0000000A
+//   layer: u32 = the ID of the layer the line belongs to (only if A is set)
+// ]
+// Layer flag defs: A = has layer
+//
+// Layers are stored in line order instead of by line ID, so lines sharing an ID keep their own layer
+
+mod line_layer_flags {
+    pub const LAYER: u8 = 1 << 0;
+}
+
+pub(in crate::formats::lrb) fn linelayers() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA | mod_flags::SCENERY,
+        read: Box::new(|cursor, output| {
+            let num_lines = cursor.read_u32::<LittleEndian>()?;
+            if num_lines == 0 {
+                return Ok(());
+            }
+
+            let track_lines = output.simulation_lines.len() + output.scenery_lines.len();
+            if usize::try_from(num_lines).ok() != Some(track_lines) {
+                return Err(TrackReadError::InvalidData {
+                    name: "line layer count".to_string(),
+                    value: num_lines.to_string(),
+                });
+            }
+
+            for line in base_lines_mut(output) {
+                let layer_flags = cursor.read_u8()?;
+                if layer_flags & line_layer_flags::LAYER != 0 {
+                    line.layer = Some(cursor.read_u32::<LittleEndian>()?);
+                }
+            }
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            let lines: Vec<_> = internal
+                .simulation_lines
+                .iter()
+                .map(|line| &line.base_line)
+                .chain(internal.scenery_lines.iter().map(|line| &line.base_line))
+                .collect();
+
+            if lines.iter().all(|line| line.layer.is_none()) {
+                buffer.write_u32::<LittleEndian>(0)?;
+                return Ok(());
+            }
+
+            buffer.write_u32::<LittleEndian>(lines.len().try_into()?)?;
+
+            for line in lines {
+                match line.layer {
+                    Some(layer) => {
+                        buffer.write_u8(line_layer_flags::LAYER)?;
+                        buffer.write_u32::<LittleEndian>(layer)?;
+                    }
+                    None => buffer.write_u8(0)?,
+                }
+            }
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
@@ -0,0 +1,78 @@
+use crate::formats::lrb::{ModHandler, common::SimulationLineIndex, mod_flags};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// count: u32 = the amount of linked lines written
+// links: linelink[count] = [
+//   id: u32 = the ID of the simulation line
+//   flags: u8 = Link flags 000000BA
+//   previous: u32 = the ID of the line connected to the start (only if A is set)
+//   next: u32 = the ID of the line connected to the end (only if B is set)
+// ]
+// Link flag defs: A = has previous line, B = has next line
+
+mod line_link_flags {
+    pub const PREVIOUS: u8 = 1 << 0;
+    pub const NEXT: u8 = 1 << 1;
+}
+
+pub(in crate::formats::lrb) fn linelinks() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA,
+        read: Box::new(|cursor, output| {
+            let num_lines = cursor.read_u32::<LittleEndian>()?;
+            let lines = SimulationLineIndex::new(output);
+
+            for _ in 0..num_lines {
+                let id = cursor.read_u32::<LittleEndian>()?;
+                let link_flags = cursor.read_u8()?;
+                let previous_line_id = if link_flags & line_link_flags::PREVIOUS != 0 {
+                    Some(cursor.read_u32::<LittleEndian>()?)
+                } else {
+                    None
+                };
+                let next_line_id = if link_flags & line_link_flags::NEXT != 0 {
+                    Some(cursor.read_u32::<LittleEndian>()?)
+                } else {
+                    None
+                };
+
+                let line = lines.find(output, id, "line link id")?;
+                line.previous_line_id = previous_line_id;
+                line.next_line_id = next_line_id;
+            }
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            let linked_lines: Vec<_> = internal
+                .simulation_lines
+                .iter()
+                .filter(|line| line.previous_line_id.is_some() || line.next_line_id.is_some())
+                .collect();
+
+            buffer.write_u32::<LittleEndian>(linked_lines.len().try_into()?)?;
+
+            for line in linked_lines {
+                let mut link_flags: u8 = 0;
+                if line.previous_line_id.is_some() {
+                    link_flags |= line_link_flags::PREVIOUS;
+                }
+                if line.next_line_id.is_some() {
+                    link_flags |= line_link_flags::NEXT;
+                }
+
+                buffer.write_u32::<LittleEndian>(line.base_line.id)?;
+                buffer.write_u8(link_flags)?;
+                if let Some(previous_line_id) = line.previous_line_id {
+                    buffer.write_u32::<LittleEndian>(previous_line_id)?;
+                }
+                if let Some(next_line_id) = line.next_line_id {
+                    buffer.write_u32::<LittleEndian>(next_line_id)?;
+                }
+            }
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
@@ -0,0 +1,57 @@
+use crate::formats::{
+    internal::LineTrigger,
+    lrb::{ModHandler, common::SimulationLineIndex, mod_flags},
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// count: u32 = the amount of line triggers written
+// triggers: linetrigger[count] = [
+//   id: u32 = the ID of the simulation line the trigger is attached to
+//   zoom: f32 = the target zoom
+//   frames: u32 = the amount of frames the zoom takes
+// ]
+
+pub(in crate::formats::lrb) fn linetriggers() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA | mod_flags::CAMERA,
+        read: Box::new(|cursor, output| {
+            let num_triggers = cursor.read_u32::<LittleEndian>()?;
+            let lines = SimulationLineIndex::new(output);
+
+            for _ in 0..num_triggers {
+                let id = cursor.read_u32::<LittleEndian>()?;
+                let zoom_target = cursor.read_f32::<LittleEndian>()?;
+                let frames = cursor.read_u32::<LittleEndian>()?;
+
+                lines.find(output, id, "line trigger id")?.line_trigger = Some(LineTrigger {
+                    zoom_target,
+                    frames,
+                });
+            }
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            let triggers: Vec<(u32, &LineTrigger)> = internal
+                .simulation_lines
+                .iter()
+                .filter_map(|line| {
+                    line.line_trigger
+                        .as_ref()
+                        .map(|trigger| (line.base_line.id, trigger))
+                })
+                .collect();
+
+            buffer.write_u32::<LittleEndian>(triggers.len().try_into()?)?;
+
+            for (id, trigger) in triggers {
+                buffer.write_u32::<LittleEndian>(id)?;
+                buffer.write_f32::<LittleEndian>(trigger.zoom_target)?;
+                buffer.write_u32::<LittleEndian>(trigger.frames)?;
+            }
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{
+    formats::lrb::{
+        ModHandler,
+        common::{write_string_u16, write_string_u32},
+        mod_flags,
+    },
+    util::{StringLength, parse_string},
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// artist: u16 length string = the track's artist
+// description: u16 length string = the track's description
+// duration: u32 = the track's duration in frames
+// script: u32 length string = the track's script
+
+pub(in crate::formats::lrb) fn metadata() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA,
+        read: Box::new(|cursor, output| {
+            output.artist = parse_string::<LittleEndian>(cursor, StringLength::U16)?;
+            output.description = parse_string::<LittleEndian>(cursor, StringLength::U16)?;
+            output.duration = cursor.read_u32::<LittleEndian>()?;
+            output.script = parse_string::<LittleEndian>(cursor, StringLength::U32)?;
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            write_string_u16(buffer, &internal.artist)?;
+            write_string_u16(buffer, &internal.description)?;
+            buffer.write_u32::<LittleEndian>(internal.duration)?;
+            write_string_u32(buffer, &internal.script)?;
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
@@ -0,0 +1,52 @@
+use crate::formats::lrb::{ModHandler, mod_flags};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// x gravity: f32 = the horizontal gravity applied to riders
+// y gravity: f32 = the vertical gravity applied to riders (remember +Y is down)
+// gravity well size: f64 = the size of the area around lines that pulls in contact points
+// flags: u8 = Physics flags 00000CBA
+// Physics flag defs: A = zero start, B = remount, C = frictionless
+
+mod physics_flags {
+    pub const ZERO_START: u8 = 1 << 0;
+    pub const REMOUNT: u8 = 1 << 1;
+    pub const FRICTIONLESS: u8 = 1 << 2;
+}
+
+pub(in crate::formats::lrb) fn physics() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA | mod_flags::PHYSICS,
+        read: Box::new(|cursor, output| {
+            output.physics.x_gravity = cursor.read_f32::<LittleEndian>()?;
+            output.physics.y_gravity = cursor.read_f32::<LittleEndian>()?;
+            output.physics.gravity_well_size = cursor.read_f64::<LittleEndian>()?;
+
+            let flags = cursor.read_u8()?;
+            output.zero_start = flags & physics_flags::ZERO_START != 0;
+            output.remount = flags & physics_flags::REMOUNT != 0;
+            output.frictionless = flags & physics_flags::FRICTIONLESS != 0;
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            buffer.write_f32::<LittleEndian>(internal.physics.x_gravity)?;
+            buffer.write_f32::<LittleEndian>(internal.physics.y_gravity)?;
+            buffer.write_f64::<LittleEndian>(internal.physics.gravity_well_size)?;
+
+            let mut flags: u8 = 0;
+            if internal.zero_start {
+                flags |= physics_flags::ZERO_START;
+            }
+            if internal.remount {
+                flags |= physics_flags::REMOUNT;
+            }
+            if internal.frictionless {
+                flags |= physics_flags::FRICTIONLESS;
+            }
+            buffer.write_u8(flags)?;
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
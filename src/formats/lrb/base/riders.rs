@@ -0,0 +1,92 @@
+use crate::formats::{
+    internal::{JsonExtensions, Rider, Vec2},
+    lrb::{ModHandler, mod_flags},
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// count: u32 = the amount of riders written
+// riders: rider[count] = [
+//   x: f64 = the x position of the rider's start
+//   y: f64 = the y position of the rider's start
+//   velocity x: f64 = the x component of the rider's start velocity
+//   velocity y: f64 = the y component of the rider's start velocity
+//   flags: u8 = Rider flags 00000CBA
+//   angle: f64 = the rider's start angle in degrees (only if A is set)
+// ]
+// Rider flag defs: A = has angle, B = has remountable setting, C = remountable
+// The first rider also sets the start position
+
+mod rider_flags {
+    pub const ANGLE: u8 = 1 << 0;
+    pub const HAS_REMOUNTABLE: u8 = 1 << 1;
+    pub const REMOUNTABLE: u8 = 1 << 2;
+}
+
+pub(in crate::formats::lrb) fn riders() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA | mod_flags::PHYSICS,
+        read: Box::new(|cursor, output| {
+            let num_riders = cursor.read_u32::<LittleEndian>()?;
+
+            for _ in 0..num_riders {
+                let x = cursor.read_f64::<LittleEndian>()?;
+                let y = cursor.read_f64::<LittleEndian>()?;
+                let velocity_x = cursor.read_f64::<LittleEndian>()?;
+                let velocity_y = cursor.read_f64::<LittleEndian>()?;
+                let flags = cursor.read_u8()?;
+                let start_angle = if flags & rider_flags::ANGLE != 0 {
+                    Some(cursor.read_f64::<LittleEndian>()?)
+                } else {
+                    None
+                };
+                let remountable = (flags & rider_flags::HAS_REMOUNTABLE != 0)
+                    .then_some(flags & rider_flags::REMOUNTABLE != 0);
+
+                output.riders.push(Rider {
+                    start_position: Vec2 { x, y },
+                    start_velocity: Vec2 {
+                        x: velocity_x,
+                        y: velocity_y,
+                    },
+                    start_angle,
+                    remountable,
+                    json_extensions: JsonExtensions::new(),
+                });
+            }
+
+            if let Some(first_rider) = output.riders.first() {
+                output.start_position = first_rider.start_position.clone();
+            }
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            buffer.write_u32::<LittleEndian>(internal.riders.len().try_into()?)?;
+
+            for rider in &internal.riders {
+                let mut flags: u8 = 0;
+                if rider.start_angle.is_some() {
+                    flags |= rider_flags::ANGLE;
+                }
+                if let Some(remountable) = rider.remountable {
+                    flags |= rider_flags::HAS_REMOUNTABLE;
+                    if remountable {
+                        flags |= rider_flags::REMOUNTABLE;
+                    }
+                }
+
+                buffer.write_f64::<LittleEndian>(rider.start_position.x)?;
+                buffer.write_f64::<LittleEndian>(rider.start_position.y)?;
+                buffer.write_f64::<LittleEndian>(rider.start_velocity.x)?;
+                buffer.write_f64::<LittleEndian>(rider.start_velocity.y)?;
+                buffer.write_u8(flags)?;
+                if let Some(start_angle) = rider.start_angle {
+                    buffer.write_f64::<LittleEndian>(start_angle)?;
+                }
+            }
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
@@ -1,56 +1,157 @@
 use crate::formats::{
-    internal::{Line, LineType, SceneryLine},
-    lrb::{ModHandler, mod_flags},
+    internal::{JsonExtensions, Line, LineType, SceneryLine},
+    lrb::{
+        LineEncoding, ModHandler,
+        common::{LineCoordinates, read_varint, read_varint_u32, write_varint},
+        mod_flags,
+    },
 };
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use once_cell::sync::Lazy;
 
+// Versions 0 and 1:
 // count: u32 = the amount of lines written
 // lines: scnline[count] = [
 //   id: u32 = the line's ID
+//   flags: u8 = Line flags 0000000A (version 1 and up)
 //   x1: f64 = the x position of the 1st point
 //   y1: f64 = the y position of the 1st point
 //   x2: f64 = the x position of the 2nd point
 //   y2: f64 = the y position of the 2nd point
+//   width: f64 = the line's width (version 1 and up, only if A is set)
 // ]
+//
+// Version 2:
+// coordinate header = see LineCoordinates
+// count: varint = the amount of lines written
+// lines: scnline[count] = [
+//   id: varint = the line's ID
+//   flags: u8 = Line flags 0000000A
+//   x1, y1, x2, y2 = the line's points, encoded as given by the coordinate header
+//   width: f64 = the line's width (only if A is set)
+// ]
+//
+// Line flag defs: A = has width
+
+mod scn_line_flags {
+    pub const WIDTH: u8 = 1 << 0;
+}
+
+pub(in crate::formats::lrb) fn scnline(version: u16) -> ModHandler {
+    let handler = ModHandler {
+        flags: mod_flags::EXTRA_DATA | mod_flags::SCENERY,
+        read: Box::new(move |cursor, output| {
+            let mut coordinates = if version >= 2 {
+                Some(LineCoordinates::read_header(cursor)?)
+            } else {
+                None
+            };
+
+            let num_lines = if version >= 2 {
+                read_varint(cursor)?
+            } else {
+                cursor.read_u32::<LittleEndian>()?.into()
+            };
+
+            for _ in 0..num_lines {
+                let id = if version >= 2 {
+                    read_varint_u32(cursor, "line id")?
+                } else {
+                    cursor.read_u32::<LittleEndian>()?
+                };
+                let line_flags = if version >= 1 { cursor.read_u8()? } else { 0 };
+                let [x1, y1, x2, y2] = match &mut coordinates {
+                    Some(coordinates) => coordinates.read(cursor)?,
+                    None => [
+                        cursor.read_f64::<LittleEndian>()?,
+                        cursor.read_f64::<LittleEndian>()?,
+                        cursor.read_f64::<LittleEndian>()?,
+                        cursor.read_f64::<LittleEndian>()?,
+                    ],
+                };
+                let width = if line_flags & scn_line_flags::WIDTH != 0 {
+                    Some(cursor.read_f64::<LittleEndian>()?)
+                } else {
+                    None
+                };
+
+                output.scenery_lines.push(SceneryLine {
+                    base_line: Line {
+                        id,
+                        x1,
+                        y1,
+                        x2,
+                        y2,
+                        line_type: LineType::GREEN,
+                        layer: None,
+                        json_extensions: JsonExtensions::new(),
+                    },
+                    width,
+                });
+            }
+
+            Ok(())
+        }),
+        write: Box::new(move |buffer, internal, options| {
+            let mut coordinates = None;
+
+            if version >= 2 {
+                coordinates = Some(LineCoordinates::write_header(
+                    buffer,
+                    options.line_encoding,
+                    internal.scenery_lines.iter().map(|line| &line.base_line),
+                )?);
+                write_varint(buffer, internal.scenery_lines.len() as u64)?;
+            } else {
+                buffer.write_u32::<LittleEndian>(internal.scenery_lines.len().try_into()?)?;
+            }
+
+            for scenery_line in &internal.scenery_lines {
+                let mut line_flags: u8 = 0;
+                if scenery_line.width.is_some() {
+                    line_flags |= scn_line_flags::WIDTH;
+                }
+
+                let base_line = &scenery_line.base_line;
+                match &mut coordinates {
+                    Some(coordinates) => {
+                        write_varint(buffer, base_line.id.into())?;
+                        buffer.write_u8(line_flags)?;
+                        coordinates.write(buffer, base_line)?;
+                    }
+                    None => {
+                        buffer.write_u32::<LittleEndian>(base_line.id)?;
+                        if version >= 1 {
+                            buffer.write_u8(line_flags)?;
+                        }
+                        buffer.write_f64::<LittleEndian>(base_line.x1)?;
+                        buffer.write_f64::<LittleEndian>(base_line.y1)?;
+                        buffer.write_f64::<LittleEndian>(base_line.x2)?;
+                        buffer.write_f64::<LittleEndian>(base_line.y2)?;
+                    }
+                }
+
+                if version >= 1
+                    && let Some(width) = scenery_line.width
+                {
+                    buffer.write_f64::<LittleEndian>(width)?;
+                }
+            }
+
+            Ok(())
+        }),
+        write_condition: None,
+    };
 
-pub(in crate::formats::lrb) static SCNLINE: Lazy<ModHandler> = Lazy::new(|| ModHandler {
-    flags: mod_flags::EXTRA_DATA | mod_flags::SCENERY,
-    read: Box::new(|cursor, output| {
-        let num_lines = cursor.read_u32::<LittleEndian>()?;
-
-        for _ in 0..num_lines {
-            let id = cursor.read_u32::<LittleEndian>()?;
-            let x1 = cursor.read_f64::<LittleEndian>()?;
-            let y1 = cursor.read_f64::<LittleEndian>()?;
-            let x2 = cursor.read_f64::<LittleEndian>()?;
-            let y2 = cursor.read_f64::<LittleEndian>()?;
-
-            output.scenery_lines.push(SceneryLine {
-                base_line: Line {
-                    id,
-                    x1,
-                    y1,
-                    x2,
-                    y2,
-                    line_type: LineType::GREEN,
-                },
-                width: None,
-            });
-        }
-
-        Ok(())
-    }),
-    write: Box::new(|buffer, internal| {
-        buffer.write_u32::<LittleEndian>(internal.scenery_lines.len() as u32)?;
-        for scenery_line in &internal.scenery_lines {
-            buffer.write_u32::<LittleEndian>(scenery_line.base_line.id)?;
-            buffer.write_f64::<LittleEndian>(scenery_line.base_line.x1)?;
-            buffer.write_f64::<LittleEndian>(scenery_line.base_line.y1)?;
-            buffer.write_f64::<LittleEndian>(scenery_line.base_line.x2)?;
-            buffer.write_f64::<LittleEndian>(scenery_line.base_line.y2)?;
-        }
-
-        Ok(())
-    }),
-});
+    // Versions 1 and 2 are only needed for widths and compact encodings, so other tracks stay
+    // readable as version 0
+    match version {
+        1 => handler.write_when(|internal, _options| {
+            internal
+                .scenery_lines
+                .iter()
+                .any(|line| line.width.is_some())
+        }),
+        2 => handler.write_when(|_internal, options| options.line_encoding != LineEncoding::F64),
+        _ => handler,
+    }
+}
@@ -1,91 +1,186 @@
 use crate::formats::{
-    internal::{Line, LineType, SimulationLine},
-    lrb::{ModHandler, mod_flags},
+    internal::{JsonExtensions, Line, LineType, SimulationLine},
+    lrb::{
+        LineEncoding, ModHandler,
+        common::{LineCoordinates, read_varint, read_varint_u32, write_varint},
+        mod_flags,
+    },
 };
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use once_cell::sync::Lazy;
 
+// Versions 0 and 1:
 // count: u32 = the amount of lines written
 // lines: simline[count] = [
 //   id: u32 = the line's ID
-//   flags: u8 = Line flags 0000DCBA
+//   flags: u8 = Line flags 000EDCBA
 //   x1: f64 = the x position of the 1st point
 //   y1: f64 = the y position of the 1st point
 //   x2: f64 = the x position of the 2nd point
 //   y2: f64 = the y position of the 2nd point
+//   multiplier: f64 = the line's acceleration multiplier (version 1 and up, only if E is set)
 // ]
-// Line flag defs: A = Red line, B = inverted, C = left extension, D = right extension
+//
+// Version 2:
+// coordinate header = see LineCoordinates
+// count: varint = the amount of lines written
+// lines: simline[count] = [
+//   id: varint = the line's ID
+//   flags: u8 = Line flags 000EDCBA
+//   x1, y1, x2, y2 = the line's points, encoded as given by the coordinate header
+//   multiplier: f64 = the line's acceleration multiplier (only if E is set)
+// ]
+//
+// Line flag defs: A = Red line, B = inverted, C = left extension, D = right extension,
+// E = has multiplier (version 1 and up)
 
 mod sim_line_flags {
     pub const RED: u8 = 1 << 0;
     pub const INVERTED: u8 = 1 << 1;
     pub const LEFT_EXTENSION: u8 = 1 << 2;
     pub const RIGHT_EXTENSION: u8 = 1 << 3;
+    pub const MULTIPLIER: u8 = 1 << 4;
 }
 
-pub(in crate::formats::lrb) static SIMLINE: Lazy<ModHandler> = Lazy::new(|| ModHandler {
-    flags: mod_flags::EXTRA_DATA | mod_flags::PHYSICS | mod_flags::SCENERY,
-    read: Box::new(|cursor, output| {
-        let num_lines = cursor.read_u32::<LittleEndian>()?;
-        for _ in 0..num_lines {
-            let id = cursor.read_u32::<LittleEndian>()?;
-            let line_flags = cursor.read_u8()?;
-            let x1 = cursor.read_f64::<LittleEndian>()?;
-            let y1 = cursor.read_f64::<LittleEndian>()?;
-            let x2 = cursor.read_f64::<LittleEndian>()?;
-            let y2 = cursor.read_f64::<LittleEndian>()?;
-            let line_type = if line_flags & sim_line_flags::RED != 0 {
-                LineType::RED
+pub(in crate::formats::lrb) fn simline(version: u16) -> ModHandler {
+    let handler = ModHandler {
+        flags: mod_flags::EXTRA_DATA | mod_flags::PHYSICS | mod_flags::SCENERY,
+        read: Box::new(move |cursor, output| {
+            let mut coordinates = if version >= 2 {
+                Some(LineCoordinates::read_header(cursor)?)
             } else {
-                LineType::BLUE
+                None
             };
-            let flipped = line_flags & sim_line_flags::INVERTED != 0;
-            let left_extension = line_flags & sim_line_flags::LEFT_EXTENSION != 0;
-            let right_extension = line_flags & sim_line_flags::RIGHT_EXTENSION != 0;
-            let base_line = Line {
-                id,
-                x1,
-                y1,
-                x2,
-                y2,
-                line_type,
+
+            let num_lines = if version >= 2 {
+                read_varint(cursor)?
+            } else {
+                cursor.read_u32::<LittleEndian>()?.into()
             };
-            output.simulation_lines.push(SimulationLine {
-                base_line,
-                flipped,
-                left_extension,
-                right_extension,
-                multiplier: None,
-            });
-        }
 
-        Ok(())
-    }),
-    write: Box::new(|buffer, internal| {
-        buffer.write_u32::<LittleEndian>(internal.simulation_lines.len() as u32)?;
-        for simulation_line in &internal.simulation_lines {
-            let mut line_flags: u8 = 0;
-            if simulation_line.base_line.line_type == LineType::RED {
-                line_flags |= sim_line_flags::RED;
-            }
-            if simulation_line.flipped {
-                line_flags |= sim_line_flags::INVERTED;
+            for _ in 0..num_lines {
+                let id = if version >= 2 {
+                    read_varint_u32(cursor, "line id")?
+                } else {
+                    cursor.read_u32::<LittleEndian>()?
+                };
+                let line_flags = cursor.read_u8()?;
+                let [x1, y1, x2, y2] = match &mut coordinates {
+                    Some(coordinates) => coordinates.read(cursor)?,
+                    None => [
+                        cursor.read_f64::<LittleEndian>()?,
+                        cursor.read_f64::<LittleEndian>()?,
+                        cursor.read_f64::<LittleEndian>()?,
+                        cursor.read_f64::<LittleEndian>()?,
+                    ],
+                };
+                let multiplier = if version >= 1 && line_flags & sim_line_flags::MULTIPLIER != 0 {
+                    Some(cursor.read_f64::<LittleEndian>()?)
+                } else {
+                    None
+                };
+                let line_type = if line_flags & sim_line_flags::RED != 0 {
+                    LineType::RED
+                } else {
+                    LineType::BLUE
+                };
+                let flipped = line_flags & sim_line_flags::INVERTED != 0;
+                let left_extension = line_flags & sim_line_flags::LEFT_EXTENSION != 0;
+                let right_extension = line_flags & sim_line_flags::RIGHT_EXTENSION != 0;
+                let base_line = Line {
+                    id,
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    line_type,
+                    layer: None,
+                    json_extensions: JsonExtensions::new(),
+                };
+                output.simulation_lines.push(SimulationLine {
+                    base_line,
+                    flipped,
+                    left_extension,
+                    right_extension,
+                    multiplier,
+                    line_trigger: None,
+                    previous_line_id: None,
+                    next_line_id: None,
+                });
             }
-            if simulation_line.left_extension {
-                line_flags |= sim_line_flags::LEFT_EXTENSION;
+
+            Ok(())
+        }),
+        write: Box::new(move |buffer, internal, options| {
+            let mut coordinates = None;
+
+            if version >= 2 {
+                coordinates = Some(LineCoordinates::write_header(
+                    buffer,
+                    options.line_encoding,
+                    internal.simulation_lines.iter().map(|line| &line.base_line),
+                )?);
+                write_varint(buffer, internal.simulation_lines.len() as u64)?;
+            } else {
+                buffer.write_u32::<LittleEndian>(internal.simulation_lines.len().try_into()?)?;
             }
-            if simulation_line.right_extension {
-                line_flags |= sim_line_flags::RIGHT_EXTENSION;
+
+            for simulation_line in &internal.simulation_lines {
+                let mut line_flags: u8 = 0;
+                if simulation_line.base_line.line_type == LineType::RED {
+                    line_flags |= sim_line_flags::RED;
+                }
+                if simulation_line.flipped {
+                    line_flags |= sim_line_flags::INVERTED;
+                }
+                if simulation_line.left_extension {
+                    line_flags |= sim_line_flags::LEFT_EXTENSION;
+                }
+                if simulation_line.right_extension {
+                    line_flags |= sim_line_flags::RIGHT_EXTENSION;
+                }
+                if version >= 1 && simulation_line.multiplier.is_some() {
+                    line_flags |= sim_line_flags::MULTIPLIER;
+                }
+
+                let base_line = &simulation_line.base_line;
+                match &mut coordinates {
+                    Some(coordinates) => {
+                        write_varint(buffer, base_line.id.into())?;
+                        buffer.write_u8(line_flags)?;
+                        coordinates.write(buffer, base_line)?;
+                    }
+                    None => {
+                        buffer.write_u32::<LittleEndian>(base_line.id)?;
+                        buffer.write_u8(line_flags)?;
+                        buffer.write_f64::<LittleEndian>(base_line.x1)?;
+                        buffer.write_f64::<LittleEndian>(base_line.y1)?;
+                        buffer.write_f64::<LittleEndian>(base_line.x2)?;
+                        buffer.write_f64::<LittleEndian>(base_line.y2)?;
+                    }
+                }
+
+                if version >= 1
+                    && let Some(multiplier) = simulation_line.multiplier
+                {
+                    buffer.write_f64::<LittleEndian>(multiplier)?;
+                }
             }
 
-            buffer.write_u32::<LittleEndian>(simulation_line.base_line.id)?;
-            buffer.write_u8(line_flags)?;
-            buffer.write_f64::<LittleEndian>(simulation_line.base_line.x1)?;
-            buffer.write_f64::<LittleEndian>(simulation_line.base_line.y1)?;
-            buffer.write_f64::<LittleEndian>(simulation_line.base_line.x2)?;
-            buffer.write_f64::<LittleEndian>(simulation_line.base_line.y2)?;
-        }
+            Ok(())
+        }),
+        write_condition: None,
+    };
 
-        Ok(())
-    }),
-});
+    // Versions 1 and 2 are only needed for multipliers and compact encodings, so other tracks stay
+    // readable as version 0
+    match version {
+        1 => handler.write_when(|internal, _options| {
+            internal
+                .simulation_lines
+                .iter()
+                .any(|line| line.multiplier.is_some())
+        }),
+        2 => handler.write_when(|_internal, options| options.line_encoding != LineEncoding::F64),
+        _ => handler,
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{
+    formats::{
+        internal::SongInfo,
+        lrb::{ModHandler, common::write_string_u16, mod_flags},
+    },
+    util::{StringLength, parse_string},
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// flags: u8 = Song flags 0000000A
+// name: u16 length string = the song's name (only if A is set)
+// offset: f64 = the offset into the song in seconds when playback starts (only if A is set)
+// Song flag defs: A = has song
+
+mod song_flags {
+    pub const SONG: u8 = 1 << 0;
+}
+
+pub(in crate::formats::lrb) fn song() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA,
+        read: Box::new(|cursor, output| {
+            let flags = cursor.read_u8()?;
+
+            if flags & song_flags::SONG != 0 {
+                let name = parse_string::<LittleEndian>(cursor, StringLength::U16)?;
+                let offset = cursor.read_f64::<LittleEndian>()?;
+                output.song_info = Some(SongInfo { name, offset });
+            }
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            match &internal.song_info {
+                Some(song_info) => {
+                    buffer.write_u8(song_flags::SONG)?;
+                    write_string_u16(buffer, &song_info.name)?;
+                    buffer.write_f64::<LittleEndian>(song_info.offset)?;
+                }
+                None => buffer.write_u8(0)?,
+            }
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
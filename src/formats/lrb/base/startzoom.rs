@@ -0,0 +1,48 @@
+use crate::{
+    TrackReadError,
+    formats::lrb::{ModHandler, mod_flags},
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// flags: u8 = Zoom flags 0000000A
+// zoom: f32 = the camera zoom when playback starts, greater than 0 (only if A is set)
+// Zoom flag defs: A = has start zoom
+
+mod zoom_flags {
+    pub const START_ZOOM: u8 = 1 << 0;
+}
+
+pub(in crate::formats::lrb) fn startzoom() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA | mod_flags::CAMERA,
+        read: Box::new(|cursor, output| {
+            let flags = cursor.read_u8()?;
+
+            if flags & zoom_flags::START_ZOOM != 0 {
+                let start_zoom = cursor.read_f32::<LittleEndian>()?;
+                if start_zoom <= 0.0 {
+                    return Err(TrackReadError::InvalidData {
+                        name: "start zoom".to_string(),
+                        value: start_zoom.to_string(),
+                    });
+                }
+
+                output.start_zoom = Some(start_zoom);
+            }
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            match internal.start_zoom {
+                Some(start_zoom) => {
+                    buffer.write_u8(zoom_flags::START_ZOOM)?;
+                    buffer.write_f32::<LittleEndian>(start_zoom)?;
+                }
+                None => buffer.write_u8(0)?,
+            }
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
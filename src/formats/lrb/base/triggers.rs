@@ -0,0 +1,90 @@
+use crate::{
+    TrackReadError,
+    formats::{
+        internal::{Trigger, TriggerEvent},
+        lrb::{
+            ModHandler,
+            common::{read_color, write_color},
+            mod_flags,
+        },
+    },
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// count: u32 = the amount of triggers written
+// triggers: trigger[count] = [
+//   start: u32 = the frame the trigger starts at
+//   end: u32 = the frame the trigger ends at
+//   type: u8 = the trigger's event type, 0 = zoom, 1 = background color, 2 = line color
+//   zoom: f32 = the target zoom (only for zoom triggers)
+//   red: u8, green: u8, blue: u8 = the target color (only for color triggers)
+// ]
+
+mod trigger_types {
+    pub const ZOOM: u8 = 0;
+    pub const BACKGROUND_COLOR: u8 = 1;
+    pub const LINE_COLOR: u8 = 2;
+}
+
+pub(in crate::formats::lrb) fn triggers() -> ModHandler {
+    ModHandler {
+        flags: mod_flags::EXTRA_DATA | mod_flags::CAMERA | mod_flags::SCENERY,
+        read: Box::new(|cursor, output| {
+            let num_triggers = cursor.read_u32::<LittleEndian>()?;
+
+            for _ in 0..num_triggers {
+                let start_frame = cursor.read_u32::<LittleEndian>()?;
+                let end_frame = cursor.read_u32::<LittleEndian>()?;
+                let event = match cursor.read_u8()? {
+                    trigger_types::ZOOM => TriggerEvent::Zoom {
+                        target: cursor.read_f32::<LittleEndian>()?,
+                    },
+                    trigger_types::BACKGROUND_COLOR => {
+                        TriggerEvent::BackgroundColor(read_color(cursor)?)
+                    }
+                    trigger_types::LINE_COLOR => TriggerEvent::LineColor(read_color(cursor)?),
+                    other => {
+                        return Err(TrackReadError::InvalidData {
+                            name: "trigger type".to_string(),
+                            value: other.to_string(),
+                        });
+                    }
+                };
+
+                output.triggers.push(Trigger {
+                    start_frame,
+                    end_frame,
+                    event,
+                });
+            }
+
+            Ok(())
+        }),
+        write: Box::new(|buffer, internal, _options| {
+            buffer.write_u32::<LittleEndian>(internal.triggers.len().try_into()?)?;
+
+            for trigger in &internal.triggers {
+                buffer.write_u32::<LittleEndian>(trigger.start_frame)?;
+                buffer.write_u32::<LittleEndian>(trigger.end_frame)?;
+
+                match trigger.event {
+                    TriggerEvent::Zoom { target } => {
+                        buffer.write_u8(trigger_types::ZOOM)?;
+                        buffer.write_f32::<LittleEndian>(target)?;
+                    }
+                    TriggerEvent::BackgroundColor(color) => {
+                        buffer.write_u8(trigger_types::BACKGROUND_COLOR)?;
+                        write_color(buffer, color)?;
+                    }
+                    TriggerEvent::LineColor(color) => {
+                        buffer.write_u8(trigger_types::LINE_COLOR)?;
+                        write_color(buffer, color)?;
+                    }
+                }
+            }
+
+            Ok(())
+        }),
+        write_condition: None,
+    }
+}
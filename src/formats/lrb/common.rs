@@ -1 +1,325 @@
+use crate::{
+    TrackReadError, TrackWriteError,
+    formats::{
+        internal::{InternalTrackFormat, Line, RGBColor, SimulationLine},
+        lrb::LineEncoding,
+    },
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    collections::HashMap,
+    io::{Cursor, Write},
+};
 
+/// Writes a string prefixed with its length as a u16
+pub(in crate::formats::lrb) fn write_string_u16(
+    buffer: &mut Cursor<Vec<u8>>,
+    string: &str,
+) -> Result<(), TrackWriteError> {
+    buffer.write_u16::<LittleEndian>(string.len().try_into()?)?;
+    buffer.write_all(string.as_bytes())?;
+
+    Ok(())
+}
+
+/// Writes a string prefixed with its length as a u32
+pub(in crate::formats::lrb) fn write_string_u32(
+    buffer: &mut Cursor<Vec<u8>>,
+    string: &str,
+) -> Result<(), TrackWriteError> {
+    buffer.write_u32::<LittleEndian>(string.len().try_into()?)?;
+    buffer.write_all(string.as_bytes())?;
+
+    Ok(())
+}
+
+pub(in crate::formats::lrb) fn read_color(
+    cursor: &mut Cursor<&[u8]>,
+) -> Result<RGBColor, TrackReadError> {
+    Ok(RGBColor {
+        red: cursor.read_u8()?,
+        green: cursor.read_u8()?,
+        blue: cursor.read_u8()?,
+    })
+}
+
+pub(in crate::formats::lrb) fn write_color(
+    buffer: &mut Cursor<Vec<u8>>,
+    color: RGBColor,
+) -> Result<(), TrackWriteError> {
+    buffer.write_u8(color.red)?;
+    buffer.write_u8(color.green)?;
+    buffer.write_u8(color.blue)?;
+
+    Ok(())
+}
+
+/// Positions of the simulation lines of a track by id, built once per section so mods referring
+/// to lines don't search every line for each reference
+pub(in crate::formats::lrb) struct SimulationLineIndex {
+    positions: HashMap<u32, usize>,
+}
+
+impl SimulationLineIndex {
+    /// Indexes the lines of a track, where the first line with an id is the one it refers to
+    pub(in crate::formats::lrb) fn new(track: &InternalTrackFormat) -> Self {
+        let mut positions = HashMap::with_capacity(track.simulation_lines.len());
+
+        for (position, line) in track.simulation_lines.iter().enumerate() {
+            positions.entry(line.base_line.id).or_insert(position);
+        }
+
+        Self { positions }
+    }
+
+    /// Finds the simulation line a mod refers to, which has to be read before the mod
+    pub(in crate::formats::lrb) fn find<'a>(
+        &self,
+        track: &'a mut InternalTrackFormat,
+        id: u32,
+        name: &str,
+    ) -> Result<&'a mut SimulationLine, TrackReadError> {
+        self.positions
+            .get(&id)
+            .and_then(|&position| track.simulation_lines.get_mut(position))
+            .ok_or_else(|| TrackReadError::InvalidData {
+                name: name.to_string(),
+                value: id.to_string(),
+            })
+    }
+}
+
+/// Iterates over the base lines of all simulation and scenery lines
+pub(in crate::formats::lrb) fn base_lines_mut(
+    track: &mut InternalTrackFormat,
+) -> impl Iterator<Item = &mut Line> {
+    track
+        .simulation_lines
+        .iter_mut()
+        .map(|line| &mut line.base_line)
+        .chain(
+            track
+                .scenery_lines
+                .iter_mut()
+                .map(|line| &mut line.base_line),
+        )
+}
+
+/// Reads an unsigned LEB128 variable length integer
+pub(in crate::formats::lrb) fn read_varint(
+    cursor: &mut Cursor<&[u8]>,
+) -> Result<u64, TrackReadError> {
+    let mut value = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let byte = cursor.read_u8()?;
+        value |= u64::from(byte & 0x7F) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(TrackReadError::InvalidData {
+        name: "varint".to_string(),
+        value: "more than 10 bytes".to_string(),
+    })
+}
+
+/// Writes an unsigned LEB128 variable length integer
+pub(in crate::formats::lrb) fn write_varint(
+    buffer: &mut Cursor<Vec<u8>>,
+    mut value: u64,
+) -> Result<(), TrackWriteError> {
+    while value >= 0x80 {
+        buffer.write_u8((value as u8 & 0x7F) | 0x80)?;
+        value >>= 7;
+    }
+    buffer.write_u8(value as u8)?;
+
+    Ok(())
+}
+
+pub(in crate::formats::lrb) fn read_varint_u32(
+    cursor: &mut Cursor<&[u8]>,
+    name: &str,
+) -> Result<u32, TrackReadError> {
+    let value = read_varint(cursor)?;
+
+    u32::try_from(value).map_err(|_| TrackReadError::InvalidData {
+        name: name.to_string(),
+        value: value.to_string(),
+    })
+}
+
+mod line_encodings {
+    pub const F64: u8 = 0;
+    pub const F32: u8 = 1;
+    pub const DELTA: u8 = 2;
+}
+
+/// Coordinates are delta encoded in units of 1 / 2^DELTA_SCALE_BITS
+const DELTA_SCALE_BITS: u8 = 8;
+
+/// Largest fixed point value that can be converted to an `f64` without rounding
+const MAX_DELTA_UNITS: f64 = (1u64 << 53) as f64;
+
+/// Reads and writes the coordinates of compact encoded lines
+///
+/// The coordinate header is:
+///
+/// encoding: u8 = 0 for f64, 1 for f32, 2 for delta
+/// lossless: u8 = 1 if the coordinates were written without losing precision, 0 otherwise
+/// scale: u8 = delta coordinates are in units of 1 / 2^scale (only for delta)
+///
+/// Each line's coordinates are then x1, y1, x2, y2 as either f64, f32, or zigzag encoded varints of
+/// the difference to the same coordinate of the previous line (starting from 0).
+pub(in crate::formats::lrb) struct LineCoordinates {
+    encoding: LineEncoding,
+    scale: f64,
+    previous: [i64; 4],
+}
+
+impl LineCoordinates {
+    pub(in crate::formats::lrb) fn read_header(
+        cursor: &mut Cursor<&[u8]>,
+    ) -> Result<Self, TrackReadError> {
+        let encoding = match cursor.read_u8()? {
+            line_encodings::F64 => LineEncoding::F64,
+            line_encodings::F32 => LineEncoding::F32,
+            line_encodings::DELTA => LineEncoding::Delta,
+            other => {
+                return Err(TrackReadError::InvalidData {
+                    name: "line encoding".to_string(),
+                    value: other.to_string(),
+                });
+            }
+        };
+
+        // Readers get the same coordinates either way, so whether they're exact is informational
+        let _lossless = cursor.read_u8()? != 0;
+
+        let scale_bits = if encoding == LineEncoding::Delta {
+            cursor.read_u8()?
+        } else {
+            0
+        };
+
+        if scale_bits > 52 {
+            return Err(TrackReadError::InvalidData {
+                name: "line coordinate scale".to_string(),
+                value: scale_bits.to_string(),
+            });
+        }
+
+        Ok(Self::new(encoding, scale_bits))
+    }
+
+    /// Writes the coordinate header for a set of lines, checking if they can be encoded losslessly
+    pub(in crate::formats::lrb) fn write_header<'a>(
+        buffer: &mut Cursor<Vec<u8>>,
+        encoding: LineEncoding,
+        lines: impl Iterator<Item = &'a Line>,
+    ) -> Result<Self, TrackWriteError> {
+        let coordinates = Self::new(encoding, DELTA_SCALE_BITS);
+        let lossless = lines
+            .flat_map(line_coordinates)
+            .all(|value| coordinates.is_lossless(value));
+
+        buffer.write_u8(match encoding {
+            LineEncoding::F64 => line_encodings::F64,
+            LineEncoding::F32 => line_encodings::F32,
+            LineEncoding::Delta => line_encodings::DELTA,
+        })?;
+        buffer.write_u8(lossless.into())?;
+
+        if encoding == LineEncoding::Delta {
+            buffer.write_u8(DELTA_SCALE_BITS)?;
+        }
+
+        Ok(coordinates)
+    }
+
+    pub(in crate::formats::lrb) fn read(
+        &mut self,
+        cursor: &mut Cursor<&[u8]>,
+    ) -> Result<[f64; 4], TrackReadError> {
+        let mut values = [0.0; 4];
+
+        for (index, value) in values.iter_mut().enumerate() {
+            *value = match self.encoding {
+                LineEncoding::F64 => cursor.read_f64::<LittleEndian>()?,
+                LineEncoding::F32 => cursor.read_f32::<LittleEndian>()?.into(),
+                LineEncoding::Delta => {
+                    let delta = zigzag_decode(read_varint(cursor)?);
+                    let units = self.previous[index].wrapping_add(delta);
+                    self.previous[index] = units;
+                    units as f64 / self.scale
+                }
+            };
+        }
+
+        Ok(values)
+    }
+
+    pub(in crate::formats::lrb) fn write(
+        &mut self,
+        buffer: &mut Cursor<Vec<u8>>,
+        line: &Line,
+    ) -> Result<(), TrackWriteError> {
+        for (index, value) in line_coordinates(line).into_iter().enumerate() {
+            match self.encoding {
+                LineEncoding::F64 => buffer.write_f64::<LittleEndian>(value)?,
+                LineEncoding::F32 => buffer.write_f32::<LittleEndian>(value as f32)?,
+                LineEncoding::Delta => {
+                    let scaled = (value * self.scale).round();
+                    if !scaled.is_finite() || scaled.abs() > MAX_DELTA_UNITS {
+                        return Err(TrackWriteError::Other {
+                            message: format!(
+                                "Line {} coordinate {} can't be delta encoded",
+                                line.id, value
+                            ),
+                        });
+                    }
+
+                    let units = scaled as i64;
+                    write_varint(buffer, zigzag_encode(units - self.previous[index]))?;
+                    self.previous[index] = units;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn new(encoding: LineEncoding, scale_bits: u8) -> Self {
+        Self {
+            encoding,
+            scale: (1u64 << scale_bits) as f64,
+            previous: [0; 4],
+        }
+    }
+
+    fn is_lossless(&self, value: f64) -> bool {
+        match self.encoding {
+            LineEncoding::F64 => true,
+            LineEncoding::F32 => f64::from(value as f32).to_bits() == value.to_bits(),
+            LineEncoding::Delta => {
+                let scaled = value * self.scale;
+                scaled.fract() == 0.0 && scaled.abs() <= MAX_DELTA_UNITS
+            }
+        }
+    }
+}
+
+fn line_coordinates(line: &Line) -> [f64; 4] {
+    [line.x1, line.y1, line.x2, line.y2]
+}
+
+fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn zigzag_decode(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
@@ -0,0 +1,134 @@
+use super::{
+    LrbVersion, ModRegistry, mod_flags,
+    reader::{ModTableEntry, read_header},
+    registry::BASE_MODS,
+};
+use crate::TrackReadError;
+
+/// Contents of an LRB file's header and mod table
+#[derive(Debug, Clone)]
+pub struct LrbInfo {
+    pub version: LrbVersion,
+    pub mods: Vec<ModInfo>,
+}
+
+/// Mod table entry of an LRB file
+#[derive(Debug, Clone)]
+pub struct ModInfo {
+    pub name: String,
+    pub version: u16,
+    pub flags: ModFlags,
+    /// Position of the mod's data section, or 0 if it has none
+    pub offset: u64,
+    /// Length of the mod's data section, or 0 if it has none
+    pub length: u64,
+    /// Whether the registry used for inspecting has a handler for this mod version
+    pub supported: bool,
+}
+
+/// Decoded `mod_flags` of a mod table entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModFlags {
+    pub required: bool,
+    pub physics: bool,
+    pub camera: bool,
+    pub scenery: bool,
+    pub extra_data: bool,
+}
+
+impl ModFlags {
+    pub fn from_bits(flags: u8) -> Self {
+        Self {
+            required: flags & mod_flags::REQUIRED != 0,
+            physics: flags & mod_flags::PHYSICS != 0,
+            camera: flags & mod_flags::CAMERA != 0,
+            scenery: flags & mod_flags::SCENERY != 0,
+            extra_data: flags & mod_flags::EXTRA_DATA != 0,
+        }
+    }
+}
+
+impl ModInfo {
+    /// Whether ignoring this mod may change how the track plays or looks
+    pub fn affects_playback(&self) -> bool {
+        self.flags.physics || self.flags.camera || self.flags.scenery
+    }
+}
+
+/// Lists the mods of an LRB file without reading their data, checking support against the base mods
+pub fn inspect(data: &[u8]) -> Result<LrbInfo, TrackReadError> {
+    inspect_with_registry(data, &BASE_MODS)
+}
+
+/// Lists the mods of an LRB file without reading their data, checking support against `registry`
+pub fn inspect_with_registry(
+    data: &[u8],
+    registry: &ModRegistry,
+) -> Result<LrbInfo, TrackReadError> {
+    let header = read_header(data)?;
+
+    let mods = header
+        .mod_table
+        .into_iter()
+        .map(|entry| {
+            let ModTableEntry {
+                name,
+                version,
+                flags,
+                offset,
+                length,
+            } = entry;
+
+            ModInfo {
+                supported: registry.contains(&name, version),
+                name,
+                version,
+                flags: ModFlags::from_bits(flags),
+                offset,
+                length,
+            }
+        })
+        .collect();
+
+    Ok(LrbInfo {
+        version: header.version,
+        mods,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{
+        internal::{InternalTrackFormat, UnsupportedLrbMod},
+        lrb,
+    };
+
+    #[test]
+    fn lists_supported_and_unsupported_mods() {
+        let mut track = InternalTrackFormat::new();
+        track.unsupported_lrb_mods.push(UnsupportedLrbMod {
+            name: "studio.camera".to_string(),
+            version: 3,
+            flags: mod_flags::EXTRA_DATA | mod_flags::CAMERA,
+            data: vec![1, 2, 3],
+        });
+        let bytes = lrb::write(&track).unwrap();
+
+        let info = inspect(&bytes).unwrap();
+
+        assert_eq!(info.version, LrbVersion::CURRENT);
+        assert!(
+            info.mods
+                .iter()
+                .any(|mod_info| mod_info.name == "base.label" && mod_info.supported)
+        );
+
+        let camera_mod = info.mods.last().unwrap();
+        assert_eq!(camera_mod.name, "studio.camera");
+        assert!(!camera_mod.supported);
+        assert!(camera_mod.flags.camera && camera_mod.affects_playback());
+        assert_eq!(camera_mod.length, 3);
+        assert_eq!(&bytes[camera_mod.offset as usize..], &[1, 2, 3]);
+    }
+}
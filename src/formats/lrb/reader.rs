@@ -1,16 +1,166 @@
-use super::{SUPPORTED_MODS, mod_flags};
+use super::{LrbVersion, ModRegistry, mod_flags, registry::BASE_MODS};
 use crate::{
     TrackReadError,
-    formats::internal::InternalTrackFormat,
-    util::{self, StringLength, parse_string},
+    formats::internal::{InternalTrackFormat, UnsupportedLrbMod},
+    util::{self, ParseLengthPrefixedStringError, StringLength, parse_string},
 };
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::{
+    io::{Cursor, ErrorKind, Read},
+    ops::Range,
+};
 
 pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
+    read_with_registry(data, &BASE_MODS)
+}
+
+/// Reads an LRB track, interpreting the mods in `registry` and keeping all others as unsupported mods
+pub fn read_with_registry(
+    data: &[u8],
+    registry: &ModRegistry,
+) -> Result<InternalTrackFormat, TrackReadError> {
     let mut parsed_track = InternalTrackFormat::new();
-    let mut cursor = Cursor::new(data);
+    let header = read_header(data)?;
+
+    // Data addresses, checked against the file before any mod is read
+    let mut sections = Vec::with_capacity(header.mod_table.len());
+    for entry in &header.mod_table {
+        let mut section = None;
+
+        if entry.flags & mod_flags::EXTRA_DATA != 0 {
+            section = Some(
+                section_range(data.len(), entry.offset, entry.length).ok_or_else(|| {
+                    TrackReadError::InvalidLrbSection {
+                        name: entry.name.clone(),
+                        version: entry.version,
+                        reason: format!(
+                            "offset {} and length {} are outside of the file ({} bytes)",
+                            entry.offset,
+                            entry.length,
+                            data.len()
+                        ),
+                    }
+                })?,
+            );
+        }
+
+        sections.push(section);
+    }
+
+    check_section_overlaps(&header.mod_table, &sections, header.mod_table_end)?;
+
+    // Supported mods are read in registration order rather than mod table order, so mods referring
+    // to other mods' data (like line triggers referring to lines) work however the file orders them
+    let mut supported_mods = Vec::new();
+
+    for (entry, section) in header.mod_table.into_iter().zip(sections) {
+        let ModTableEntry {
+            name,
+            version,
+            flags,
+            ..
+        } = entry;
+
+        let Some(mod_handler) = registry.get(&name, version) else {
+            if flags & mod_flags::REQUIRED != 0 {
+                return Err(TrackReadError::Other {
+                    message: format!("Required mod not supported: {} v{}", name, version),
+                });
+            }
+
+            // println!("[WARNING] This mod is not supported: {} v{}", name, version);
+
+            if flags & mod_flags::SCENERY != 0 {
+                // println!("Ignoring it may affect scenery rendering.");
+            }
+            if flags & mod_flags::CAMERA != 0 {
+                // println!("Ignoring it may affect camera functionality.");
+            }
+            if flags & mod_flags::PHYSICS != 0 {
+                // println!("Ignoring it may affect track physics.");
+            }
+
+            // Keep the mod's data as is so it can be written back out
+            let mod_data = section.map_or_else(Vec::new, |range| data[range].to_vec());
+
+            parsed_track.unsupported_lrb_mods.push(UnsupportedLrbMod {
+                name,
+                version,
+                flags,
+                data: mod_data,
+            });
+
+            continue;
+        };
 
+        // We're done if there's no more data
+        if let Some(range) = section {
+            let registry_position = registry.position(&name, version);
+            supported_mods.push((registry_position, name, version, mod_handler, range));
+        }
+    }
+
+    supported_mods.sort_by_key(|(registry_position, ..)| *registry_position);
+
+    for (_, name, version, mod_handler, range) in supported_mods {
+        // The handler only sees its own section, and has to read all of it
+        let section_data = &data[range];
+        let mut section_cursor = Cursor::new(section_data);
+
+        (mod_handler.read)(&mut section_cursor, &mut parsed_track).map_err(|err| match err {
+            TrackReadError::Io(io_err)
+            | TrackReadError::StringParsing(ParseLengthPrefixedStringError::Io(io_err))
+                if io_err.kind() == ErrorKind::UnexpectedEof =>
+            {
+                TrackReadError::InvalidLrbSection {
+                    name: name.clone(),
+                    version,
+                    reason: "read past the end of the section".to_string(),
+                }
+            }
+            other => other,
+        })?;
+
+        let consumed = section_cursor.position();
+        if consumed != section_data.len() as u64 {
+            return Err(TrackReadError::InvalidLrbSection {
+                name,
+                version,
+                reason: format!(
+                    "only {} of {} bytes were read",
+                    consumed,
+                    section_data.len()
+                ),
+            });
+        }
+    }
+
+    Ok(parsed_track)
+}
+
+/// Header and mod table of an LRB file
+pub(super) struct LrbHeader {
+    pub(super) version: LrbVersion,
+    pub(super) mod_table: Vec<ModTableEntry>,
+    /// Position right after the mod table, where data sections can start
+    pub(super) mod_table_end: usize,
+}
+
+/// Mod table entry, where offset and length are 0 if the mod has no data section
+pub(super) struct ModTableEntry {
+    pub(super) name: String,
+    pub(super) version: u16,
+    pub(super) flags: u8,
+    pub(super) offset: u64,
+    pub(super) length: u64,
+}
+
+/// Reads the version of an LRB file from its header, without checking if it is supported
+pub fn read_version(data: &[u8]) -> Result<LrbVersion, TrackReadError> {
+    read_magic_and_version(&mut Cursor::new(data))
+}
+
+fn read_magic_and_version(cursor: &mut Cursor<&[u8]>) -> Result<LrbVersion, TrackReadError> {
     // Magic number
     let mut magic_number = [0u8; 3];
     cursor.read_exact(&mut magic_number)?;
@@ -23,12 +173,28 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
     }
 
     // Version
-    let _version = cursor.read_u8()?;
+    Ok(LrbVersion(cursor.read_u8()?))
+}
+
+/// Reads the header and mod table of an LRB file, without reading any mod data
+pub(super) fn read_header(data: &[u8]) -> Result<LrbHeader, TrackReadError> {
+    let mut cursor = Cursor::new(data);
+
+    let version = read_magic_and_version(&mut cursor)?;
+
+    // Other major versions may lay out the header and mod table differently, so they can't be read
+    if !version.is_supported() {
+        return Err(TrackReadError::UnsupportedLrbVersion {
+            major: version.major(),
+            supported: LrbVersion::CURRENT.major(),
+        });
+    }
 
     // Number of mods
     let mod_count = cursor.read_u16::<LittleEndian>()?;
 
     // Mod table
+    let mut mod_table = Vec::with_capacity(mod_count.into());
     for _ in 0..mod_count {
         // Name
         let name = parse_string::<LittleEndian>(&mut cursor, StringLength::U8)?;
@@ -40,59 +206,202 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
         let flags = cursor.read_u8()?;
 
         let mut offset = 0u64;
-        let mut _length = 0u64; // TODO: length is unused
+        let mut length = 0u64;
 
         // Data address
         if flags & mod_flags::EXTRA_DATA != 0 {
             offset = cursor.read_u64::<LittleEndian>()?;
-            _length = cursor.read_u64::<LittleEndian>()?;
+            length = cursor.read_u64::<LittleEndian>()?;
         }
 
-        if !SUPPORTED_MODS.contains_key(&(name.as_str(), version)) {
-            if flags & mod_flags::REQUIRED != 0 {
-                return Err(TrackReadError::Other {
-                    message: format!("Required mod not supported: {} v{}", name, version),
-                });
-            }
+        mod_table.push(ModTableEntry {
+            name,
+            version,
+            flags,
+            offset,
+            length,
+        });
+    }
 
-            // TODO: Include these flags in the internal format
-            // println!("[WARNING] This mod is not supported: {} v{}", name, version);
+    Ok(LrbHeader {
+        version,
+        mod_table,
+        mod_table_end: usize::try_from(cursor.position()).unwrap_or(data.len()),
+    })
+}
 
-            if flags & mod_flags::SCENERY != 0 {
-                // println!("Ignoring it may affect scenery rendering.");
-            }
-            if flags & mod_flags::CAMERA != 0 {
-                // println!("Ignoring it may affect camera functionality.");
-            }
-            if flags & mod_flags::PHYSICS != 0 {
-                // println!("Ignoring it may affect track physics.");
-            }
+/// Converts a mod's data address into a byte range, or `None` if it lies outside of the file
+fn section_range(data_length: usize, offset: u64, length: u64) -> Option<Range<usize>> {
+    let start = usize::try_from(offset).ok()?;
+    let end = start.checked_add(usize::try_from(length).ok()?)?;
+
+    (end <= data_length).then_some(start..end)
+}
+
+/// Makes sure no data section overlaps another one or the header and mod table before them
+fn check_section_overlaps(
+    mod_table: &[ModTableEntry],
+    sections: &[Option<Range<usize>>],
+    mod_table_end: usize,
+) -> Result<(), TrackReadError> {
+    let mut sections: Vec<(&Range<usize>, &ModTableEntry)> = sections
+        .iter()
+        .zip(mod_table)
+        .filter_map(|(section, entry)| section.as_ref().map(|range| (range, entry)))
+        .filter(|(range, _)| !range.is_empty())
+        .collect();
+    sections.sort_by_key(|(range, _)| range.start);
+
+    let mut previous: Option<(&Range<usize>, &ModTableEntry)> = None;
+    for (range, entry) in sections {
+        if range.start < mod_table_end {
+            return Err(TrackReadError::InvalidLrbSection {
+                name: entry.name.clone(),
+                version: entry.version,
+                reason: "overlaps the header or mod table".to_string(),
+            });
         }
 
-        // We're done if there's no more data
-        if flags & mod_flags::EXTRA_DATA == 0 {
-            continue;
+        if let Some((previous_range, previous_entry)) = previous
+            && range.start < previous_range.end
+        {
+            return Err(TrackReadError::InvalidLrbSection {
+                name: entry.name.clone(),
+                version: entry.version,
+                reason: format!(
+                    "overlaps the section of {} v{}",
+                    previous_entry.name, previous_entry.version
+                ),
+            });
         }
 
-        // Record the current position and jump to the extra data position
-        let current_position = cursor.stream_position()?;
-        cursor.seek(SeekFrom::Start(offset))?;
+        previous = Some((range, entry));
+    }
 
-        let mod_identifier = (name.as_str(), version);
-        match SUPPORTED_MODS.get(&mod_identifier) {
-            Some(mod_handler) => {
-                (mod_handler.read)(&mut cursor, &mut parsed_track)?;
-            }
-            None => {
-                return Err(TrackReadError::InvalidData {
-                    name: "mod_identifier".to_string(),
-                    value: format!("{} v{}", mod_identifier.0, mod_identifier.1),
-                });
-            }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{
+        internal::{GridVersion, LineTrigger, TrackBuilder, Vec2},
+        lrb::{base, write_with_registry},
+    };
+
+    /// Builds an LRB file with a single v0 mod entry pointing at the given data address
+    fn single_mod_track(
+        name: &str,
+        flags: u8,
+        offset: u64,
+        length: u64,
+        section: &[u8],
+    ) -> Vec<u8> {
+        let mut bytes = b"LRB\x00\x01\x00".to_vec();
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.push(flags);
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        bytes.extend_from_slice(&length.to_le_bytes());
+        bytes.extend_from_slice(section);
+        bytes
+    }
+
+    /// Builds an LRB file with a single `base.gridver` entry pointing at the given data address
+    fn gridver_track(offset: u64, length: u64, section: &[u8]) -> Vec<u8> {
+        let flags = mod_flags::EXTRA_DATA | mod_flags::PHYSICS;
+        single_mod_track("base.gridver", flags, offset, length, section)
+    }
+
+    fn section_error_reason(bytes: &[u8]) -> String {
+        match read(bytes) {
+            Err(TrackReadError::InvalidLrbSection { reason, .. }) => reason,
+            other => panic!("expected section error, got {:?}", other.map(|_| ())),
         }
+    }
+
+    #[test]
+    fn reads_section_by_length() {
+        let track = read(&gridver_track(38, 1, &[1])).unwrap();
 
-        cursor.seek(SeekFrom::Start(current_position))?;
+        assert!(matches!(track.grid_version, GridVersion::V6_1));
     }
 
-    Ok(parsed_track)
+    #[test]
+    fn rejects_invalid_sections() {
+        assert!(section_error_reason(&gridver_track(38, 2, &[1])).contains("outside"));
+        assert!(section_error_reason(&gridver_track(30, 1, &[1])).contains("mod table"));
+        assert!(section_error_reason(&gridver_track(38, 2, &[1, 0])).contains("1 of 2"));
+        assert!(section_error_reason(&gridver_track(38, 0, &[])).contains("past the end"));
+    }
+
+    #[test]
+    fn strings_must_fit_in_their_section() {
+        // Artist claims 5 bytes, but only 2 are left in the section
+        let section = [5, 0, b'a', b'b'];
+        let track = single_mod_track("base.metadata", mod_flags::EXTRA_DATA, 39, 4, &section);
+
+        assert!(section_error_reason(&track).contains("past the end"));
+    }
+
+    #[test]
+    fn checks_header_version() {
+        let current = gridver_track(38, 1, &[1]);
+        assert_eq!(read_version(&current).unwrap(), LrbVersion::CURRENT);
+        assert!(read(&current).is_ok());
+
+        let mut newer_major = gridver_track(38, 1, &[1]);
+        newer_major[3] = LrbVersion::new(1, 0).0;
+        assert_eq!(read_version(&newer_major).unwrap().major(), 1);
+        assert!(matches!(
+            read(&newer_major),
+            Err(TrackReadError::UnsupportedLrbVersion {
+                major: 1,
+                supported: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn reads_newer_minor_versions_with_the_current_layout() {
+        let mut newer_minor = gridver_track(38, 1, &[1]);
+        newer_minor[3] = LrbVersion::new(0, 1).0;
+        assert_eq!(read_version(&newer_minor).unwrap().minor(), 1);
+        assert!(matches!(
+            read(&newer_minor).unwrap().grid_version,
+            GridVersion::V6_1
+        ));
+
+        // Mods added by the minor version are kept as unsupported mods
+        let mut new_mod = single_mod_track("base.future", mod_flags::EXTRA_DATA, 37, 1, &[7]);
+        new_mod[3] = LrbVersion::new(0, 1).0;
+        let track = read(&new_mod).unwrap();
+        assert_eq!(track.unsupported_lrb_mods[0].name, "base.future");
+        assert_eq!(track.unsupported_lrb_mods[0].data, vec![7]);
+    }
+
+    #[test]
+    fn reads_line_references_regardless_of_mod_order() {
+        let mut track = TrackBuilder::new()
+            .blue_line(Vec2 { x: 0.0, y: 0.0 }, Vec2 { x: 10.0, y: 0.0 })
+            .build()
+            .unwrap();
+        track.simulation_lines[0].line_trigger = Some(LineTrigger {
+            zoom_target: 2.0,
+            frames: 40,
+        });
+
+        // Writes the line triggers ahead of the lines they refer to
+        let mut reversed = ModRegistry::empty();
+        reversed.register("base.linetriggers", 0, base::linetriggers());
+        reversed.register("base.simline", 0, base::simline(0));
+
+        let read_back = read(&write_with_registry(&track, &reversed).unwrap()).unwrap();
+
+        assert_eq!(
+            read_back.simulation_lines[0].line_trigger,
+            track.simulation_lines[0].line_trigger
+        );
+    }
 }
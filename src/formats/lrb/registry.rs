@@ -0,0 +1,236 @@
+use super::{ModHandler, base};
+use once_cell::sync::Lazy;
+
+/// Registry of the base mods, used when no registry is given
+pub(super) static BASE_MODS: Lazy<ModRegistry> = Lazy::new(ModRegistry::new);
+
+/// Set of mods the LRB reader and writer support, keyed by name and version
+///
+/// Mods are written and read in the order they were registered, whatever their order in the
+/// file, so mods whose data refers to other mods (like line triggers referring to lines) are
+/// registered after them with [`ModRegistry::register_after`]. Every registered version can be read, but only one version of each
+/// mod is written: the highest one whose [`ModHandler::write_when`] condition holds for the track.
+///
+/// ```
+/// use byteorder::{ReadBytesExt, WriteBytesExt};
+/// use lr_formatter_rs::{
+///     internal::InternalTrackFormat,
+///     lrb::{self, ModHandler, ModRegistry, mod_flags},
+/// };
+///
+/// // Stores the length of the script as an example
+/// let mut registry = ModRegistry::new();
+/// registry.register(
+///     "studio.scriptlen",
+///     0,
+///     ModHandler::new(
+///         mod_flags::EXTRA_DATA,
+///         |cursor, output| {
+///             let length = cursor.read_u8()?;
+///             output.script = "x".repeat(length.into());
+///             Ok(())
+///         },
+///         |cursor, input, _options| {
+///             cursor.write_u8(input.script.len().try_into()?)?;
+///             Ok(())
+///         },
+///     ),
+/// );
+///
+/// let mut track = InternalTrackFormat::new();
+/// track.script = "abc".to_string();
+///
+/// let bytes = lrb::write_with_registry(&track, &registry).unwrap();
+/// let read_back = lrb::read_with_registry(&bytes, &registry).unwrap();
+///
+/// assert_eq!(read_back.script, "xxx");
+/// assert!(read_back.unsupported_lrb_mods.is_empty());
+/// ```
+pub struct ModRegistry {
+    mods: Vec<RegisteredMod>,
+}
+
+struct RegisteredMod {
+    name: String,
+    version: u16,
+    handler: ModHandler,
+}
+
+impl Default for ModRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModRegistry {
+    /// Creates a registry containing the base mods
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+
+        registry.register("base.gridver", 0, base::gridver());
+        registry.register("base.label", 0, base::label());
+        registry.register("base.metadata", 0, base::metadata());
+        registry.register("base.scnline", 0, base::scnline(0));
+        registry.register("base.scnline", 1, base::scnline(1));
+        registry.register("base.scnline", 2, base::scnline(2));
+        registry.register("base.simline", 0, base::simline(0));
+        registry.register("base.simline", 1, base::simline(1));
+        registry.register("base.simline", 2, base::simline(2));
+        registry.register("base.startoffset", 0, base::startoffset());
+        registry.register("base.riders", 0, base::riders());
+        registry.register("base.physics", 0, base::physics());
+        registry.register("base.triggers", 0, base::triggers());
+        registry.register("base.layers", 0, base::layers());
+        registry.register("base.colors", 0, base::colors());
+        registry.register("base.startzoom", 0, base::startzoom());
+        registry.register("base.song", 0, base::song());
+        registry.register_after(
+            "base.linetriggers",
+            0,
+            base::linetriggers(),
+            &["base.simline"],
+        );
+        registry.register_after("base.linelinks", 0, base::linelinks(), &["base.simline"]);
+        registry.register_after(
+            "base.linelayers",
+            0,
+            base::linelayers(),
+            &["base.simline", "base.scnline"],
+        );
+
+        registry
+    }
+
+    /// Creates a registry without any mods
+    pub fn empty() -> Self {
+        Self { mods: Vec::new() }
+    }
+
+    /// Adds a handler for a mod version, returning the handler it replaced if there was one
+    ///
+    /// A replaced handler keeps its position in the registration order.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        version: u16,
+        handler: ModHandler,
+    ) -> Option<ModHandler> {
+        let name = name.into();
+
+        match self.position(&name, version) {
+            Some(index) => Some(std::mem::replace(&mut self.mods[index].handler, handler)),
+            None => {
+                self.mods.push(RegisteredMod {
+                    name,
+                    version,
+                    handler,
+                });
+                None
+            }
+        }
+    }
+
+    /// Adds a handler like [`ModRegistry::register`] for a mod whose data refers to the data of
+    /// the mods named in `dependencies`, so it's read after them
+    ///
+    /// # Panics
+    ///
+    /// Panics if a dependency isn't registered ahead of the mod.
+    pub fn register_after(
+        &mut self,
+        name: impl Into<String>,
+        version: u16,
+        handler: ModHandler,
+        dependencies: &[&str],
+    ) -> Option<ModHandler> {
+        let name = name.into();
+        let index = self.position(&name, version).unwrap_or(self.mods.len());
+
+        for dependency in dependencies {
+            assert!(
+                self.mods[..index]
+                    .iter()
+                    .any(|registered| registered.name == *dependency),
+                "Mod {} v{} has to be registered after {}",
+                name,
+                version,
+                dependency
+            );
+        }
+
+        self.register(name, version, handler)
+    }
+
+    /// Removes the handler for a mod version, returning it if there was one
+    pub fn unregister(&mut self, name: &str, version: u16) -> Option<ModHandler> {
+        let index = self.position(name, version)?;
+
+        Some(self.mods.remove(index).handler)
+    }
+
+    pub fn get(&self, name: &str, version: u16) -> Option<&ModHandler> {
+        let index = self.position(name, version)?;
+
+        Some(&self.mods[index].handler)
+    }
+
+    pub fn contains(&self, name: &str, version: u16) -> bool {
+        self.position(name, version).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.mods.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mods.is_empty()
+    }
+
+    /// Iterates over the registered mods as `(name, version, handler)` in registration order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u16, &ModHandler)> {
+        self.mods.iter().map(|registered| {
+            (
+                registered.name.as_str(),
+                registered.version,
+                &registered.handler,
+            )
+        })
+    }
+
+    /// Iterates over the highest registered version of each mod, in registration order
+    pub fn latest(&self) -> impl Iterator<Item = (&str, u16, &ModHandler)> {
+        self.iter().filter(|(name, version, _)| {
+            !self
+                .mods
+                .iter()
+                .any(|other| other.name == *name && other.version > *version)
+        })
+    }
+
+    /// Position of a mod version in the registration order
+    pub(super) fn position(&self, name: &str, version: u16) -> Option<usize> {
+        self.mods
+            .iter()
+            .position(|registered| registered.name == name && registered.version == version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_mods_are_registered_after_their_dependencies() {
+        let registry = ModRegistry::new();
+
+        assert!(registry.position("base.simline", 0) < registry.position("base.linetriggers", 0));
+        assert!(registry.position("base.scnline", 0) < registry.position("base.linelayers", 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "has to be registered after base.simline")]
+    fn rejects_mods_registered_before_their_dependencies() {
+        let mut registry = ModRegistry::empty();
+        registry.register_after("base.linelinks", 0, base::linelinks(), &["base.simline"]);
+    }
+}
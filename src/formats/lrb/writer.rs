@@ -1,65 +1,549 @@
-use super::{SUPPORTED_MODS, mod_flags};
-use crate::{TrackWriteError, formats::internal::InternalTrackFormat};
-use byteorder::{LittleEndian, WriteBytesExt};
-use std::{
-    collections::HashMap,
-    io::{Cursor, Seek, SeekFrom, Write},
+use super::{LrbVersion, ModHandler, ModRegistry, WriteOptions, mod_flags, registry::BASE_MODS};
+use crate::{
+    TrackWriteError,
+    formats::internal::{InternalTrackFormat, UnsupportedLrbMod},
 };
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::io::{Cursor, Seek, SeekFrom, Write};
 
 pub fn write(internal: &InternalTrackFormat) -> Result<Vec<u8>, TrackWriteError> {
+    write_with_registry(internal, &BASE_MODS)
+}
+
+/// Writes an LRB track containing the highest applicable version of every mod in `registry`
+/// (see [`ModHandler::write_when`]), followed by the track's unsupported mods
+///
+/// The output only depends on the track and the registry, so writing the same track twice gives identical bytes.
+pub fn write_with_registry(
+    internal: &InternalTrackFormat,
+    registry: &ModRegistry,
+) -> Result<Vec<u8>, TrackWriteError> {
+    write_with_options(internal, registry, &WriteOptions::default())
+}
+
+/// Writes an LRB track like [`write_with_registry`], with options for how mods are written
+pub fn write_with_options(
+    internal: &InternalTrackFormat,
+    registry: &ModRegistry,
+    options: &WriteOptions,
+) -> Result<Vec<u8>, TrackWriteError> {
     let mut cursor = Cursor::new(Vec::new());
-    let mut mod_table_entry_offsets: HashMap<String, u64> = HashMap::new();
+    let mods = select_mods(internal, registry, options)?;
+
+    // An unsupported mod with the same name and version as a written mod holds the same data, so
+    // only the written one is kept. Other versions, like ones from newer editors, hold data this
+    // crate couldn't read and are written back unchanged.
+    let unsupported_mods: Vec<&UnsupportedLrbMod> = internal
+        .unsupported_lrb_mods
+        .iter()
+        .filter(|unsupported_mod| {
+            !mods.iter().any(|selected_mod| {
+                selected_mod.name == unsupported_mod.name
+                    && selected_mod.version == unsupported_mod.version
+            })
+        })
+        .collect();
 
     // Magic number
     cursor.write_all(b"LRB")?;
 
     // Version
-    cursor.write_u8(0)?;
+    cursor.write_u8(LrbVersion::CURRENT.0)?;
 
     // Number of mods
-    cursor.write_u16::<LittleEndian>(5)?;
+    let mod_count = mods.len() + unsupported_mods.len();
+    cursor.write_u16::<LittleEndian>(mod_count.try_into()?)?;
+
+    // Mod table, in registration order followed by unsupported mods in the order they were read
+    let mut mod_entry_offsets: Vec<Option<u64>> = Vec::with_capacity(mods.len());
+    for selected_mod in &mods {
+        mod_entry_offsets.push(write_mod_table_entry(
+            &mut cursor,
+            selected_mod.name,
+            selected_mod.version,
+            selected_mod.flags,
+        )?);
+    }
+
+    let mut unsupported_mod_entry_offsets: Vec<Option<u64>> =
+        Vec::with_capacity(unsupported_mods.len());
+    for unsupported_mod in &unsupported_mods {
+        unsupported_mod_entry_offsets.push(write_mod_table_entry(
+            &mut cursor,
+            &unsupported_mod.name,
+            unsupported_mod.version,
+            unsupported_mod.flags,
+        )?);
+    }
+
+    // Mod data sections, in the same order as the mod table, where unsupported mods are written
+    // back exactly as they were read
+    let sections = mods
+        .iter()
+        .map(|selected_mod| selected_mod.section.as_slice())
+        .zip(mod_entry_offsets)
+        .chain(
+            unsupported_mods
+                .iter()
+                .map(|unsupported_mod| unsupported_mod.data.as_slice())
+                .zip(unsupported_mod_entry_offsets),
+        );
+
+    for (section, entry_offset) in sections {
+        if let Some(offset) = entry_offset {
+            let section_start = cursor.stream_position()?;
+            cursor.write_all(section)?;
+            write_data_address(&mut cursor, offset, section_start)?;
+        }
+    }
+
+    Ok(cursor.into_inner())
+}
+
+/// Registered mod chosen to be written, with its data section already written out
+struct SelectedMod<'a> {
+    name: &'a str,
+    version: u16,
+    flags: u8,
+    section: Vec<u8>,
+}
 
-    // Mod table
-    for mod_identifier in SUPPORTED_MODS.keys() {
-        // Name
-        let name = mod_identifier.0;
-        cursor.write_u8(name.len().try_into()?)?;
-        cursor.write_all(name.as_bytes())?;
+/// Picks the version of each registered mod to write and writes its data section, leaving out
+/// empty sections if the options ask for it
+fn select_mods<'a>(
+    internal: &InternalTrackFormat,
+    registry: &'a ModRegistry,
+    options: &WriteOptions,
+) -> Result<Vec<SelectedMod<'a>>, TrackWriteError> {
+    for name in options.pinned_versions.keys().chain(&options.required_mods) {
+        if !registry
+            .iter()
+            .any(|(registered_name, _, _)| registered_name == name)
+        {
+            return Err(TrackWriteError::Other {
+                message: format!("Mod selected in write options is not registered: {}", name),
+            });
+        }
+    }
 
-        // Version
-        let version = mod_identifier.1;
-        cursor.write_u16::<LittleEndian>(version)?;
+    // A section that matches the one of a new track holds nothing a reader doesn't already assume
+    let default_track = InternalTrackFormat::new();
+    let mut selected_mods = Vec::new();
 
-        // Flags
-        let flags = SUPPORTED_MODS[mod_identifier].flags;
-        cursor.write_u8(flags)?;
+    for (name, _, _) in registry.latest() {
+        let (version, mod_handler) = match options.pinned_versions.get(name) {
+            Some(&version) => {
+                let mod_handler =
+                    registry
+                        .get(name, version)
+                        .ok_or_else(|| TrackWriteError::Other {
+                            message: format!(
+                                "Pinned mod version is not registered: {} v{}",
+                                name, version
+                            ),
+                        })?;
+                (version, mod_handler)
+            }
+            None => match default_version(registry, name, internal, options) {
+                Some(default) => default,
+                None => continue,
+            },
+        };
 
-        // Data address
+        let required = options
+            .required_mods
+            .iter()
+            .any(|required_name| required_name == name);
+        let mut flags = mod_handler.flags;
+        if required {
+            flags |= mod_flags::REQUIRED;
+        }
+
+        let mut section = Vec::new();
         if flags & mod_flags::EXTRA_DATA != 0 {
-            mod_table_entry_offsets.insert(name.to_string(), cursor.stream_position()?);
+            section = write_section(mod_handler, internal, options)?;
 
-            // Allocate space for data address information
-            cursor.write_u64::<LittleEndian>(0)?;
-            cursor.write_u64::<LittleEndian>(0)?;
+            if options.skip_empty_sections
+                && !required
+                && section == write_section(mod_handler, &default_track, options)?
+            {
+                continue;
+            }
         }
+
+        selected_mods.push(SelectedMod {
+            name,
+            version,
+            flags,
+            section,
+        });
+    }
+
+    Ok(selected_mods)
+}
+
+/// Picks the highest registered version of a mod whose write condition holds, or `None` if no
+/// version applies to the track
+fn default_version<'a>(
+    registry: &'a ModRegistry,
+    name: &str,
+    internal: &InternalTrackFormat,
+    options: &WriteOptions,
+) -> Option<(u16, &'a ModHandler)> {
+    registry
+        .iter()
+        .filter(|(registered_name, _, mod_handler)| {
+            *registered_name == name
+                && mod_handler
+                    .write_condition
+                    .as_ref()
+                    .is_none_or(|condition| condition(internal, options))
+        })
+        .map(|(_, version, mod_handler)| (version, mod_handler))
+        .max_by_key(|(version, _)| *version)
+}
+
+fn write_section(
+    mod_handler: &ModHandler,
+    internal: &InternalTrackFormat,
+    options: &WriteOptions,
+) -> Result<Vec<u8>, TrackWriteError> {
+    let mut section = Cursor::new(Vec::new());
+    (mod_handler.write)(&mut section, internal, options)?;
+
+    Ok(section.into_inner())
+}
+
+/// Writes a mod table entry, returning the position of its data address if it has extra data
+fn write_mod_table_entry(
+    cursor: &mut Cursor<Vec<u8>>,
+    name: &str,
+    version: u16,
+    flags: u8,
+) -> Result<Option<u64>, TrackWriteError> {
+    // Name
+    cursor.write_u8(name.len().try_into()?)?;
+    cursor.write_all(name.as_bytes())?;
+
+    // Version
+    cursor.write_u16::<LittleEndian>(version)?;
+
+    // Flags
+    cursor.write_u8(flags)?;
+
+    // Data address
+    if flags & mod_flags::EXTRA_DATA == 0 {
+        return Ok(None);
     }
 
-    for mod_identifer in SUPPORTED_MODS.keys() {
-        let section_start = cursor.stream_position()?;
-        let name = mod_identifer.0;
-        let writer = &SUPPORTED_MODS[mod_identifer].write;
-        (writer)(&mut cursor, &internal)?;
+    let offset = cursor.stream_position()?;
 
-        let section_end = cursor.stream_position()?;
-        let section_length = section_end - section_start;
+    // Allocate space for data address information
+    cursor.write_u64::<LittleEndian>(0)?;
+    cursor.write_u64::<LittleEndian>(0)?;
+
+    Ok(Some(offset))
+}
 
-        if let Some(&offset) = mod_table_entry_offsets.get(name) {
-            cursor.seek(SeekFrom::Start(offset))?;
-            cursor.write_u64::<LittleEndian>(section_start)?;
-            cursor.write_u64::<LittleEndian>(section_length)?;
-            cursor.seek(SeekFrom::Start(section_end))?;
+/// Fills in the data address of a mod table entry for the section that was just written
+fn write_data_address(
+    cursor: &mut Cursor<Vec<u8>>,
+    entry_offset: u64,
+    section_start: u64,
+) -> Result<(), TrackWriteError> {
+    let section_end = cursor.stream_position()?;
+    let section_length = section_end - section_start;
+
+    cursor.seek(SeekFrom::Start(entry_offset))?;
+    cursor.write_u64::<LittleEndian>(section_start)?;
+    cursor.write_u64::<LittleEndian>(section_length)?;
+    cursor.seek(SeekFrom::Start(section_end))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{
+        internal::{
+            Layer, LineTrigger, RGBColor, Rider, TrackBuilder, Trigger, TriggerEvent, Vec2,
+        },
+        lrb::{self, LineEncoding},
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn output_is_reproducible() {
+        let mut track = TrackBuilder::new()
+            .title("Reproducible")
+            .artist("Artist")
+            .duration(400)
+            .blue_line(Vec2 { x: 0.0, y: 0.0 }, Vec2 { x: 10.0, y: 0.0 })
+            .green_line(Vec2 { x: 0.0, y: 5.0 }, Vec2 { x: 10.0, y: 5.0 })
+            .build()
+            .unwrap();
+        track.simulation_lines[0].multiplier = Some(2.0);
+        track.scenery_lines[0].width = Some(0.5);
+
+        let first = write(&track).unwrap();
+        let read_back = lrb::read(&first).unwrap();
+        let second = write(&read_back).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(read_back.artist, "Artist");
+        assert_eq!(read_back.duration, 400);
+        assert_eq!(read_back.simulation_lines[0].multiplier, Some(2.0));
+        assert_eq!(read_back.scenery_lines[0].width, Some(0.5));
+        assert_eq!(
+            u16::from_le_bytes([first[4], first[5]]) as usize,
+            BASE_MODS.latest().count()
+        );
+    }
+
+    #[test]
+    fn preserves_extended_track_properties() {
+        let mut track = TrackBuilder::new()
+            .red_line(Vec2 { x: 0.0, y: 0.0 }, Vec2 { x: 10.0, y: 0.0 })
+            .green_line(Vec2 { x: 0.0, y: 5.0 }, Vec2 { x: 10.0, y: 5.0 })
+            .rider(Rider {
+                start_position: Vec2 { x: 1.0, y: 2.0 },
+                start_angle: Some(45.0),
+                remountable: Some(true),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        track.simulation_lines[0].line_trigger = Some(LineTrigger {
+            zoom_target: 2.0,
+            frames: 40,
+        });
+        track.scenery_lines[0].base_line.layer = Some(3);
+        track.layers.push(Layer {
+            id: 3,
+            name: "Scenery".to_string(),
+            visible: true,
+            editable: false,
+            folder_id: None,
+        });
+        track.triggers.push(Trigger {
+            start_frame: 10,
+            end_frame: 20,
+            event: TriggerEvent::LineColor(RGBColor {
+                red: 255,
+                green: 0,
+                blue: 0,
+            }),
+        });
+        track.physics.y_gravity = 0.5;
+        track.remount = true;
+        track.start_zoom = Some(3.0);
+
+        let read_back = lrb::read(&write(&track).unwrap()).unwrap();
+
+        assert_eq!(read_back.riders.len(), 1);
+        assert_eq!(read_back.riders[0].remountable, Some(true));
+        assert_eq!(read_back.start_position.y, 2.0);
+        assert_eq!(
+            read_back.simulation_lines[0].line_trigger,
+            track.simulation_lines[0].line_trigger
+        );
+        assert_eq!(read_back.scenery_lines[0].base_line.layer, Some(3));
+        assert_eq!(read_back.layers[0].name, "Scenery");
+        assert_eq!(read_back.triggers, track.triggers);
+        assert_eq!(read_back.physics, track.physics);
+        assert!(read_back.remount);
+        assert_eq!(read_back.start_zoom, Some(3.0));
+    }
+
+    #[test]
+    fn line_layers_stay_with_lines_sharing_an_id() {
+        let mut track = TrackBuilder::new()
+            .blue_line(Vec2 { x: 0.0, y: 0.0 }, Vec2 { x: 10.0, y: 0.0 })
+            .green_line(Vec2 { x: 0.0, y: 5.0 }, Vec2 { x: 10.0, y: 5.0 })
+            .green_line(Vec2 { x: 0.0, y: 9.0 }, Vec2 { x: 10.0, y: 9.0 })
+            .build()
+            .unwrap();
+        track.scenery_lines[0].base_line.id = track.simulation_lines[0].base_line.id;
+        track.simulation_lines[0].base_line.layer = Some(1);
+        track.scenery_lines[0].base_line.layer = Some(2);
+
+        let read_back = lrb::read(&write(&track).unwrap()).unwrap();
+
+        assert_eq!(read_back.simulation_lines[0].base_line.layer, Some(1));
+        assert_eq!(read_back.scenery_lines[0].base_line.layer, Some(2));
+        assert_eq!(read_back.scenery_lines[1].base_line.layer, None);
+    }
+
+    #[test]
+    fn compact_line_encodings_round_trip() {
+        let track = TrackBuilder::new()
+            .blue_line(Vec2 { x: -2.5, y: 0.0 }, Vec2 { x: 10.0, y: 0.25 })
+            .red_line(Vec2 { x: 10.0, y: 0.25 }, Vec2 { x: 1e6, y: -3.0 })
+            .green_line(Vec2 { x: 0.5, y: 5.0 }, Vec2 { x: 10.0, y: 5.0 })
+            .build()
+            .unwrap();
+        let full_size = write(&track).unwrap().len();
+
+        for line_encoding in [LineEncoding::F32, LineEncoding::Delta] {
+            let options = WriteOptions {
+                line_encoding,
+                ..Default::default()
+            };
+            let bytes = write_with_options(&track, &BASE_MODS, &options).unwrap();
+            let read_back = lrb::read(&bytes).unwrap();
+
+            assert!(bytes.len() < full_size);
+            assert_eq!(read_back.simulation_lines[1].base_line.x2, 1e6);
+            assert_eq!(read_back.simulation_lines[1].base_line.id, 1);
+            assert_eq!(read_back.scenery_lines[0].base_line.x1, 0.5);
         }
     }
 
-    Ok(cursor.into_inner())
+    #[test]
+    fn options_select_written_mods() {
+        let track = TrackBuilder::new()
+            .title("Minimal")
+            .blue_line(Vec2 { x: 0.0, y: 0.0 }, Vec2 { x: 10.0, y: 0.0 })
+            .build()
+            .unwrap();
+        let options = WriteOptions {
+            skip_empty_sections: true,
+            required_mods: vec!["base.simline".to_string()],
+            pinned_versions: HashMap::from([("base.simline".to_string(), 0)]),
+            ..Default::default()
+        };
+
+        let bytes = write_with_options(&track, &BASE_MODS, &options).unwrap();
+        let mods: Vec<(String, u16, bool)> = lrb::inspect(&bytes)
+            .unwrap()
+            .mods
+            .into_iter()
+            .map(|mod_info| (mod_info.name, mod_info.version, mod_info.flags.required))
+            .collect();
+
+        assert_eq!(
+            mods,
+            vec![
+                ("base.label".to_string(), 0, false),
+                ("base.simline".to_string(), 0, true),
+            ]
+        );
+        assert_eq!(lrb::read(&bytes).unwrap().simulation_lines.len(), 1);
+    }
+
+    /// Versions of `base.simline` and `base.scnline` in the mod table of an LRB file
+    fn line_mod_versions(bytes: &[u8]) -> (u16, u16) {
+        let mods = lrb::inspect(bytes).unwrap().mods;
+        let version_of = |name: &str| {
+            mods.iter()
+                .find(|mod_info| mod_info.name == name)
+                .map(|mod_info| mod_info.version)
+                .unwrap()
+        };
+
+        (version_of("base.simline"), version_of("base.scnline"))
+    }
+
+    #[test]
+    fn line_mod_versions_follow_track() {
+        let registry = ModRegistry::new();
+        let mut track = TrackBuilder::new()
+            .blue_line(Vec2 { x: 0.0, y: 0.0 }, Vec2 { x: 10.0, y: 0.0 })
+            .green_line(Vec2 { x: 0.0, y: 5.0 }, Vec2 { x: 10.0, y: 5.0 })
+            .build()
+            .unwrap();
+        let bytes = write_with_registry(&track, &registry).unwrap();
+        assert_eq!(line_mod_versions(&bytes), (0, 0));
+
+        track.simulation_lines[0].multiplier = Some(2.0);
+        let bytes = write_with_registry(&track, &registry).unwrap();
+        assert_eq!(line_mod_versions(&bytes), (1, 0));
+
+        track.scenery_lines[0].width = Some(2.0);
+        let bytes = write_with_registry(&track, &registry).unwrap();
+        assert_eq!(line_mod_versions(&bytes), (1, 1));
+
+        let options = WriteOptions {
+            line_encoding: LineEncoding::Delta,
+            ..Default::default()
+        };
+        let bytes = write_with_options(&track, &registry, &options).unwrap();
+        assert_eq!(line_mod_versions(&bytes), (2, 2));
+    }
+
+    #[test]
+    fn unsupported_mods_round_trip() {
+        let mut track = InternalTrackFormat::new();
+        track.unsupported_lrb_mods = vec![
+            UnsupportedLrbMod {
+                name: "other.data".to_string(),
+                version: 3,
+                flags: mod_flags::EXTRA_DATA | mod_flags::SCENERY,
+                data: vec![1, 2, 3],
+            },
+            UnsupportedLrbMod {
+                name: "other.flag".to_string(),
+                version: 0,
+                flags: mod_flags::PHYSICS,
+                data: Vec::new(),
+            },
+        ];
+
+        let read_back = lrb::read(&write(&track).unwrap()).unwrap();
+        let mods: Vec<(&str, u16, u8, &[u8])> = read_back
+            .unsupported_lrb_mods
+            .iter()
+            .map(|unsupported_mod| {
+                (
+                    unsupported_mod.name.as_str(),
+                    unsupported_mod.version,
+                    unsupported_mod.flags,
+                    unsupported_mod.data.as_slice(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            mods,
+            vec![
+                (
+                    "other.data",
+                    3,
+                    mod_flags::EXTRA_DATA | mod_flags::SCENERY,
+                    &[1, 2, 3][..]
+                ),
+                ("other.flag", 0, mod_flags::PHYSICS, &[][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn newer_versions_of_registered_mods_round_trip() {
+        let newer_simline = UnsupportedLrbMod {
+            name: "base.simline".to_string(),
+            version: 7,
+            flags: mod_flags::EXTRA_DATA | mod_flags::PHYSICS,
+            data: vec![1, 2, 3, 4],
+        };
+        let mut track = InternalTrackFormat::new();
+        track.unsupported_lrb_mods = vec![
+            newer_simline.clone(),
+            UnsupportedLrbMod {
+                name: "base.label".to_string(),
+                version: 0,
+                flags: mod_flags::EXTRA_DATA,
+                data: Vec::new(),
+            },
+        ];
+
+        let read_back = lrb::read(&write(&track).unwrap()).unwrap();
+
+        assert_eq!(read_back.unsupported_lrb_mods.len(), 1);
+        let unsupported_mod = &read_back.unsupported_lrb_mods[0];
+        assert_eq!(unsupported_mod.name, newer_simline.name);
+        assert_eq!(unsupported_mod.version, newer_simline.version);
+        assert_eq!(unsupported_mod.flags, newer_simline.flags);
+        assert_eq!(unsupported_mod.data, newer_simline.data);
+    }
 }
@@ -17,7 +17,5 @@ pub fn get_track_count(data: &[u8]) -> u32 {
 
     // HACK: We assume header size is constant, and track list length will always be at 0x2C - 0x2F
     let _ = cursor.seek(std::io::SeekFrom::Start(0x2C));
-    let num_tracks = cursor.read_u32::<BigEndian>().unwrap_or(0);
-
-    num_tracks
+    cursor.read_u32::<BigEndian>().unwrap_or(0)
 }
@@ -17,11 +17,8 @@ pub(in crate::formats::sol) fn deserialize<R: Read>(
 ) -> Result<Vec<Amf0Value>, Amf0DeserializationError> {
     let mut results = vec![];
 
-    loop {
-        match read_next_value(bytes)? {
-            Some(x) => results.push(x),
-            None => break,
-        };
+    while let Some(x) = read_next_value(bytes)? {
+        results.push(x);
     }
 
     Ok(results)
@@ -89,11 +86,8 @@ fn parse_string<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0Deserialization
 fn parse_object<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
     let mut properties = HashMap::new();
 
-    loop {
-        match parse_object_property(bytes)? {
-            Some(property) => properties.insert(property.label, property.value),
-            None => break,
-        };
+    while let Some(property) = parse_object_property(bytes)? {
+        properties.insert(property.label, property.value);
     }
 
     let deserialized_value = Amf0Value::Object(properties);
@@ -182,10 +176,7 @@ mod tests {
         let mut input = Cursor::new(vector);
         let result = deserialize(&mut input).unwrap();
 
-        let mut array = Vec::new();
-
-        array.push(Amf0Value::Number(1.0));
-        array.push(Amf0Value::Number(2.0));
+        let array = vec![Amf0Value::Number(1.0), Amf0Value::Number(2.0)];
 
         let expected = vec![Amf0Value::StrictArray(array)];
         assert_eq!(result, expected);
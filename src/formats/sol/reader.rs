@@ -4,7 +4,10 @@ use std::io::{Cursor, Read};
 use crate::{
     TrackReadError,
     formats::{
-        internal::{GridVersion, InternalTrackFormat, Line, LineType, SceneryLine, SimulationLine},
+        internal::{
+            GridVersion, InternalTrackFormat, JsonExtensions, Line, LineType, SceneryLine,
+            SimulationLine,
+        },
         sol::amf0::deserialize,
     },
     util::{StringLength, bytes_to_hex_string, parse_string},
@@ -18,7 +21,7 @@ pub fn read(data: &[u8], track_index: Option<u32>) -> Result<InternalTrackFormat
     let mut magic_number = [0u8; 2];
     cursor.read_exact(&mut magic_number)?;
 
-    if &magic_number != &[0x00, 0xBF] {
+    if magic_number != [0x00, 0xBF] {
         return Err(TrackReadError::InvalidData {
             name: "magic number".to_string(),
             value: bytes_to_hex_string(&magic_number),
@@ -31,7 +34,7 @@ pub fn read(data: &[u8], track_index: Option<u32>) -> Result<InternalTrackFormat
     let mut tag = [0u8; 4];
     cursor.read_exact(&mut tag)?;
 
-    if &tag != &[b'T', b'C', b'S', b'O'] {
+    if &tag != b"TCSO" {
         return Err(TrackReadError::InvalidData {
             name: "header tag".to_string(),
             value: bytes_to_hex_string(&tag),
@@ -40,7 +43,7 @@ pub fn read(data: &[u8], track_index: Option<u32>) -> Result<InternalTrackFormat
 
     let mut marker = [0u8; 6];
     cursor.read_exact(&mut marker)?;
-    if &marker != &[0x00, 0x04, 0x00, 0x00, 0x00, 0x00] {
+    if marker != [0x00, 0x04, 0x00, 0x00, 0x00, 0x00] {
         return Err(TrackReadError::InvalidData {
             name: "header marker".to_string(),
             value: bytes_to_hex_string(&marker),
@@ -173,9 +176,8 @@ pub fn read(data: &[u8], track_index: Option<u32>) -> Result<InternalTrackFormat
                 })?;
     }
 
-    if let Some(_) = target_track.get("trackData") {
-        // TODO: Enable zero start (don't bother parsing)
-    }
+    // LRA only writes track data when zero start is enabled
+    parsed_track.zero_start = target_track.contains_key("trackData");
 
     if let Some(val) = target_track.get("data") {
         let lines_list =
@@ -278,6 +280,18 @@ pub fn read(data: &[u8], track_index: Option<u32>) -> Result<InternalTrackFormat
                     value: format!("{:?}", flipped_amf),
                 })?;
 
+            // Neighbor ids are only meaningful on the sides where the line is extended
+            let previous_line_id = line
+                .get("6")
+                .and_then(|amf| amf.clone().get_number())
+                .filter(|_| left_extension)
+                .and_then(number_to_line_id);
+            let next_line_id = line
+                .get("7")
+                .and_then(|amf| amf.clone().get_number())
+                .filter(|_| right_extension)
+                .and_then(number_to_line_id);
+
             let id_amf = line.get("8").ok_or(TrackReadError::InvalidData {
                 name: "line".to_string(),
                 value: format!("{:?}", line),
@@ -291,14 +305,7 @@ pub fn read(data: &[u8], track_index: Option<u32>) -> Result<InternalTrackFormat
                     value: format!("{:?}", id_amf),
                 })?;
 
-            let unsafe_id =
-                if id_float.is_finite() && id_float >= 0.0 && id_float <= u32::MAX as f64 {
-                    Some(id_float as u32)
-                } else {
-                    None
-                };
-
-            let id = match unsafe_id {
+            let id = match number_to_line_id(id_float) {
                 Some(val) => val,
                 None => {
                     return Err(TrackReadError::InvalidData {
@@ -341,6 +348,8 @@ pub fn read(data: &[u8], track_index: Option<u32>) -> Result<InternalTrackFormat
                 y2,
                 id,
                 line_type,
+                layer: None,
+                json_extensions: JsonExtensions::new(),
             };
 
             if line_type == LineType::GREEN {
@@ -355,6 +364,9 @@ pub fn read(data: &[u8], track_index: Option<u32>) -> Result<InternalTrackFormat
                     left_extension,
                     right_extension,
                     multiplier: None,
+                    line_trigger: None,
+                    previous_line_id,
+                    next_line_id,
                 });
             }
         }
@@ -362,3 +374,12 @@ pub fn read(data: &[u8], track_index: Option<u32>) -> Result<InternalTrackFormat
 
     Ok(parsed_track)
 }
+
+/// Converts an amf number into a line id, or `None` if it isn't a valid u32
+fn number_to_line_id(number: f64) -> Option<u32> {
+    if number.is_finite() && number >= 0.0 && number <= u32::MAX as f64 {
+        Some(number as u32)
+    } else {
+        None
+    }
+}
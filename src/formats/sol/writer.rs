@@ -57,8 +57,14 @@ pub fn write(internal: &InternalTrackFormat) -> Result<Vec<u8>, TrackWriteError>
         line_object.insert("3".to_string(), Amf0Value::Number(line.base_line.y2));
         line_object.insert("4".to_string(), Amf0Value::Number(ext));
         line_object.insert("5".to_string(), Amf0Value::Number(inv));
-        line_object.insert("6".to_string(), Amf0Value::Number(0.0));
-        line_object.insert("7".to_string(), Amf0Value::Number(0.0));
+        line_object.insert(
+            "6".to_string(),
+            Amf0Value::Number(line_id_to_number(line.previous_line_id)),
+        );
+        line_object.insert(
+            "7".to_string(),
+            Amf0Value::Number(line_id_to_number(line.next_line_id)),
+        );
         line_object.insert("8".to_string(), Amf0Value::Number(line.base_line.id as f64));
         line_object.insert("9".to_string(), Amf0Value::Number(numeric_line_type));
         lines_vec.push(line_object);
@@ -94,23 +100,6 @@ pub fn write(internal: &InternalTrackFormat) -> Result<Vec<u8>, TrackWriteError>
         Amf0Value::Number(internal.start_position.y),
     );
 
-    let mut first_null_array = HashMap::new();
-    first_null_array.insert("0".to_string(), Amf0Value::Null);
-    first_null_array.insert("1".to_string(), Amf0Value::Null);
-    first_null_array.insert("2".to_string(), Amf0Value::Null);
-
-    let mut second_null_array = HashMap::new();
-    second_null_array.insert("0".to_string(), Amf0Value::Null);
-    second_null_array.insert("1".to_string(), Amf0Value::Null);
-    second_null_array.insert("2".to_string(), Amf0Value::Null);
-    second_null_array.insert("3".to_string(), Amf0Value::Null);
-    second_null_array.insert("4".to_string(), Amf0Value::Boolean(true));
-
-    let mut track_data = HashMap::new();
-    track_data.insert("0".to_string(), Amf0Value::Null);
-    track_data.insert("1".to_string(), Amf0Value::ECMAArray(first_null_array));
-    track_data.insert("2".to_string(), Amf0Value::ECMAArray(second_null_array));
-
     let mut track = HashMap::new();
     track.insert(
         "label".to_string(),
@@ -127,8 +116,26 @@ pub fn write(internal: &InternalTrackFormat) -> Result<Vec<u8>, TrackWriteError>
     track.insert("level".to_string(), Amf0Value::Number(line_count));
     track.insert("data".to_string(), Amf0Value::ECMAArray(line_array_object));
 
-    // TODO: Insert if zero start
-    // track.insert("trackData".to_string(), Amf0Value::ECMAArray(track_data));
+    if internal.zero_start {
+        let mut first_null_array = HashMap::new();
+        first_null_array.insert("0".to_string(), Amf0Value::Null);
+        first_null_array.insert("1".to_string(), Amf0Value::Null);
+        first_null_array.insert("2".to_string(), Amf0Value::Null);
+
+        let mut second_null_array = HashMap::new();
+        second_null_array.insert("0".to_string(), Amf0Value::Null);
+        second_null_array.insert("1".to_string(), Amf0Value::Null);
+        second_null_array.insert("2".to_string(), Amf0Value::Null);
+        second_null_array.insert("3".to_string(), Amf0Value::Null);
+        second_null_array.insert("4".to_string(), Amf0Value::Boolean(true));
+
+        let mut track_data = HashMap::new();
+        track_data.insert("0".to_string(), Amf0Value::Null);
+        track_data.insert("1".to_string(), Amf0Value::ECMAArray(first_null_array));
+        track_data.insert("2".to_string(), Amf0Value::ECMAArray(second_null_array));
+
+        track.insert("trackData".to_string(), Amf0Value::ECMAArray(track_data));
+    }
 
     let mut track_list = HashMap::new();
     track_list.insert("0".to_string(), Amf0Value::Object(track));
@@ -146,3 +153,85 @@ pub fn write(internal: &InternalTrackFormat) -> Result<Vec<u8>, TrackWriteError>
 
     Ok(cursor.into_inner())
 }
+
+/// Converts an optional line id into an amf number, using -1 for missing lines since 0 is a valid id
+fn line_id_to_number(id: Option<u32>) -> f64 {
+    id.map_or(-1.0, f64::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{
+        internal::{JsonExtensions, Line, SimulationLine},
+        sol,
+    };
+
+    fn round_trip(track: &InternalTrackFormat) -> InternalTrackFormat {
+        sol::read(&write(track).unwrap(), None).unwrap()
+    }
+
+    fn simulation_line(id: u32, x: f64) -> SimulationLine {
+        SimulationLine {
+            base_line: Line {
+                id,
+                x1: x,
+                y1: 0.0,
+                x2: x + 10.0,
+                y2: 0.0,
+                line_type: LineType::BLUE,
+                layer: None,
+                json_extensions: JsonExtensions::new(),
+            },
+            flipped: false,
+            left_extension: false,
+            right_extension: false,
+            multiplier: None,
+            line_trigger: None,
+            previous_line_id: None,
+            next_line_id: None,
+        }
+    }
+
+    #[test]
+    fn zero_start_round_trips_through_track_data() {
+        let mut track = InternalTrackFormat::new();
+
+        assert!(!round_trip(&track).zero_start);
+
+        track.zero_start = true;
+        assert!(round_trip(&track).zero_start);
+    }
+
+    #[test]
+    fn line_links_round_trip() {
+        let mut track = InternalTrackFormat::new();
+        track.simulation_lines = vec![
+            simulation_line(1, 0.0),
+            simulation_line(2, 10.0),
+            simulation_line(3, 30.0),
+        ];
+        track.simulation_lines[0].right_extension = true;
+        track.simulation_lines[0].next_line_id = Some(2);
+        track.simulation_lines[1].left_extension = true;
+        track.simulation_lines[1].previous_line_id = Some(1);
+        track.simulation_lines[2].left_extension = true;
+        track.simulation_lines[2].right_extension = true;
+
+        let read_back = round_trip(&track);
+        let line = |id: u32| {
+            read_back
+                .simulation_lines
+                .iter()
+                .find(|line| line.base_line.id == id)
+                .unwrap()
+        };
+
+        assert_eq!(line(1).next_line_id, Some(2));
+        assert_eq!(line(1).previous_line_id, None);
+        assert_eq!(line(2).previous_line_id, Some(1));
+        assert_eq!(line(2).next_line_id, None);
+        assert_eq!(line(3).previous_line_id, None);
+        assert_eq!(line(3).next_line_id, None);
+    }
+}
@@ -8,6 +8,7 @@ mod writer;
 pub use reader::read;
 pub use writer::write;
 
+use crate::formats::internal::JsonExtensions;
 use serde::{Deserialize, Serialize};
 use serde_boolean::option_bool_from_any;
 
@@ -18,6 +19,7 @@ use serde_boolean::option_bool_from_any;
 // Extended bitflags 0b000000ba
 // a: 1 if starting/left extension
 // b: 1 if ending/right extension
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 enum LRAJsonArrayLine {
     BlueLine(u32, f64, f64, f64, f64, u8, bool),
@@ -25,7 +27,6 @@ enum LRAJsonArrayLine {
     GreenLine(u32, f64, f64, f64, f64),
 }
 
-#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug)]
 struct LRAJsonLegacyZoomTrigger {
     #[serde(rename = "ID")]
@@ -37,7 +38,6 @@ struct LRAJsonLegacyZoomTrigger {
 
 // Faulty U32's are used here whenever properties are -999, which
 // represents undefined/unused in the LRA json trigger format
-#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug)]
 struct LRAJsonTrigger {
     #[serde(rename = "triggerType")]
@@ -45,7 +45,7 @@ struct LRAJsonTrigger {
     start: u32,
     end: u32,
     #[serde(rename = "zoomTarget")]
-    zoom_target: FaultyU32,
+    zoom_target: f32,
     #[serde(rename = "backgroundRed")]
     background_red: Option<FaultyU32>,
     #[serde(rename = "backgroundGreen")]
@@ -60,6 +60,9 @@ struct LRAJsonTrigger {
     line_blue: Option<FaultyU32>,
 }
 
+// Value used by LRA json triggers for properties that don't apply to the trigger type
+const LRA_TRIGGER_UNUSED: i32 = -999;
+
 // A u32 value that can take the range of a normal u32, or negative for invalid (for parsing some json fields)
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -110,13 +113,17 @@ struct JsonLine {
     multiplier: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     width: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    layer: Option<u32>,
+    #[serde(flatten)]
+    extensions: JsonExtensions,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct JsonLayer {
     id: u32,
-    #[serde(rename = "type")]
-    layer_type: u8,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    layer_type: Option<u8>, // 0 or undefined for layers, 1 for folders
     name: String,
     visible: bool,
     editable: bool,
@@ -134,8 +141,14 @@ struct JsonRider {
     start_vel: Vec2,
     #[serde(rename = "startAngle", skip_serializing_if = "Option::is_none")]
     angle: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "option_bool_from_any"
+    )]
     remountable: Option<bool>,
+    #[serde(flatten)]
+    extensions: JsonExtensions,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -157,6 +170,12 @@ struct JsonTrack {
     start_zoom: Option<f32>,
     #[serde(rename = "zeroStart", skip_serializing_if = "Option::is_none")]
     zero_start: Option<bool>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "option_bool_from_any"
+    )]
+    frictionless: Option<bool>,
     #[serde(rename = "triggers", skip_serializing_if = "Option::is_none")]
     line_based_triggers: Option<Vec<LRAJsonLegacyZoomTrigger>>,
     #[serde(rename = "gameTriggers", skip_serializing_if = "Option::is_none")]
@@ -179,4 +198,7 @@ struct JsonTrack {
     line_color_green: Option<u32>,
     #[serde(rename = "lineB", skip_serializing_if = "Option::is_none")]
     line_color_blue: Option<u32>,
+    // Any properties not listed above, so they aren't lost when rewriting the track
+    #[serde(flatten)]
+    extensions: JsonExtensions,
 }
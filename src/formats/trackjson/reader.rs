@@ -1,9 +1,11 @@
-use super::JsonTrack;
+use super::{FaultyU32, JsonTrack};
 use crate::{
     TrackReadError,
     formats::{
         internal::{
-            GridVersion, InternalTrackFormat, Line, LineType, SceneryLine, SimulationLine, Vec2,
+            GridVersion, InternalTrackFormat, JsonExtensions, Layer, LayerFolder, Line,
+            LineTrigger, LineType, RGBColor, Rider, SceneryLine, SimulationLine, Trigger,
+            TriggerEvent, Vec2,
         },
         trackjson::LRAJsonArrayLine,
     },
@@ -48,6 +50,8 @@ pub fn read(json_str: &str) -> Result<InternalTrackFormat, TrackReadError> {
                 x2: line.x2,
                 y2: line.y2,
                 line_type,
+                layer: line.layer,
+                json_extensions: line.extensions,
             };
 
             if line.line_type == 2 {
@@ -76,6 +80,9 @@ pub fn read(json_str: &str) -> Result<InternalTrackFormat, TrackReadError> {
                     left_extension,
                     right_extension,
                     multiplier: line.multiplier,
+                    line_trigger: None,
+                    previous_line_id: None,
+                    next_line_id: None,
                 });
             }
         }
@@ -93,6 +100,8 @@ pub fn read(json_str: &str) -> Result<InternalTrackFormat, TrackReadError> {
                         x2,
                         y2,
                         line_type: LineType::BLUE,
+                        layer: None,
+                        json_extensions: JsonExtensions::new(),
                     };
 
                     parsed_track.simulation_lines.push(SimulationLine {
@@ -101,6 +110,9 @@ pub fn read(json_str: &str) -> Result<InternalTrackFormat, TrackReadError> {
                         left_extension: extended == 1 || extended == 3,
                         right_extension: extended == 2 || extended == 3,
                         multiplier: None,
+                        line_trigger: None,
+                        previous_line_id: None,
+                        next_line_id: None,
                     });
                 }
                 LRAJsonArrayLine::RedLine(
@@ -122,6 +134,8 @@ pub fn read(json_str: &str) -> Result<InternalTrackFormat, TrackReadError> {
                         x2,
                         y2,
                         line_type: LineType::RED,
+                        layer: None,
+                        json_extensions: JsonExtensions::new(),
                     };
 
                     parsed_track.simulation_lines.push(SimulationLine {
@@ -130,6 +144,9 @@ pub fn read(json_str: &str) -> Result<InternalTrackFormat, TrackReadError> {
                         left_extension: extended == 1 || extended == 3,
                         right_extension: extended == 2 || extended == 3,
                         multiplier: Some(multiplier as f64),
+                        line_trigger: None,
+                        previous_line_id: None,
+                        next_line_id: None,
                     });
                 }
                 LRAJsonArrayLine::GreenLine(id, x1, y1, x2, y2) => {
@@ -140,6 +157,8 @@ pub fn read(json_str: &str) -> Result<InternalTrackFormat, TrackReadError> {
                         x2,
                         y2,
                         line_type: LineType::GREEN,
+                        layer: None,
+                        json_extensions: JsonExtensions::new(),
                     };
 
                     parsed_track.scenery_lines.push(SceneryLine {
@@ -151,12 +170,96 @@ pub fn read(json_str: &str) -> Result<InternalTrackFormat, TrackReadError> {
         }
     }
 
+    if let Some(layer_list) = track.layers {
+        for layer in layer_list {
+            match layer.layer_type.unwrap_or(0) {
+                0 => {
+                    let folder_id = match layer.folder_id {
+                        Some(FaultyU32::Valid(folder_id)) => Some(folder_id),
+                        _ => None,
+                    };
+
+                    parsed_track.layers.push(Layer {
+                        id: layer.id,
+                        name: layer.name,
+                        visible: layer.visible,
+                        editable: layer.editable,
+                        folder_id,
+                    });
+                }
+                1 => {
+                    parsed_track.layer_folders.push(LayerFolder {
+                        id: layer.id,
+                        name: layer.name,
+                        visible: layer.visible,
+                        editable: layer.editable,
+                        size: layer.size.unwrap_or(0),
+                    });
+                }
+                other => {
+                    return Err(TrackReadError::InvalidData {
+                        name: "layer type".to_string(),
+                        value: other.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(line_trigger_list) = track.line_based_triggers {
+        for trigger in line_trigger_list.iter().filter(|trigger| trigger.zoom) {
+            // Triggers left behind on deleted lines are dropped, since editors never activate them
+            let Some(line) = parsed_track
+                .simulation_lines
+                .iter_mut()
+                .find(|line| line.base_line.id == trigger.id)
+            else {
+                continue;
+            };
+
+            line.line_trigger = Some(LineTrigger {
+                zoom_target: trigger.target,
+                frames: trigger.frames,
+            });
+        }
+    }
+
     parsed_track.start_position = Vec2 {
         x: track.start_pos.x,
         y: track.start_pos.y,
     };
 
+    if let Some(rider_list) = track.riders {
+        for rider in rider_list {
+            parsed_track.riders.push(Rider {
+                start_position: Vec2 {
+                    x: rider.start_pos.x,
+                    y: rider.start_pos.y,
+                },
+                start_velocity: Vec2 {
+                    x: rider.start_vel.x,
+                    y: rider.start_vel.y,
+                },
+                start_angle: rider.angle,
+                remountable: rider.remountable,
+                json_extensions: rider.extensions,
+            });
+        }
+    }
+
+    if let Some(first_rider) = parsed_track.riders.first() {
+        parsed_track.start_position = first_rider.start_position.clone();
+    }
+
+    // The format has no track-wide remount setting, so it's only set when every rider is remountable
+    parsed_track.remount = !parsed_track.riders.is_empty()
+        && parsed_track
+            .riders
+            .iter()
+            .all(|rider| rider.remountable == Some(true));
+
     parsed_track.title = track.label;
+    parsed_track.json_extensions = track.extensions;
 
     if let Some(creator) = track.creator {
         parsed_track.artist = creator;
@@ -171,8 +274,432 @@ pub fn read(json_str: &str) -> Result<InternalTrackFormat, TrackReadError> {
         parsed_track.script = script;
     }
 
-    // TODO: These fields need parsing into the internal format still
-    // start_zoom, zero_start, line_based_triggers, time_based_triggers, x_gravity, y_gravity, gravity_well_size,
-    // background_color_red/green/blue, line_color_red/green/blue
+    if let Some(trigger_list) = track.time_based_triggers {
+        for (i, trigger) in trigger_list.iter().enumerate() {
+            let event = match trigger.trigger_type {
+                0 => TriggerEvent::Zoom {
+                    target: trigger.zoom_target,
+                },
+                1 => TriggerEvent::BackgroundColor(parse_trigger_color(
+                    [
+                        &trigger.background_red,
+                        &trigger.background_green,
+                        &trigger.background_blue,
+                    ],
+                    "trigger background color",
+                )?),
+                2 => TriggerEvent::LineColor(parse_trigger_color(
+                    [&trigger.line_red, &trigger.line_green, &trigger.line_blue],
+                    "trigger line color",
+                )?),
+                other => {
+                    return Err(TrackReadError::InvalidData {
+                        name: format!("triggers {} type", i),
+                        value: other.to_string(),
+                    });
+                }
+            };
+
+            parsed_track.triggers.push(Trigger {
+                start_frame: trigger.start,
+                end_frame: trigger.end,
+                event,
+            });
+        }
+    }
+
+    if let Some(x_gravity) = track.x_gravity {
+        parsed_track.physics.x_gravity = x_gravity;
+    }
+    if let Some(y_gravity) = track.y_gravity {
+        parsed_track.physics.y_gravity = y_gravity;
+    }
+    if let Some(gravity_well_size) = track.gravity_well_size {
+        parsed_track.physics.gravity_well_size = gravity_well_size;
+    }
+
+    parsed_track.background_color = parse_color_properties(
+        [
+            track.background_color_red,
+            track.background_color_green,
+            track.background_color_blue,
+        ],
+        "background color",
+    )?;
+    parsed_track.line_color = parse_color_properties(
+        [
+            track.line_color_red,
+            track.line_color_green,
+            track.line_color_blue,
+        ],
+        "line color",
+    )?;
+
+    if let Some(start_zoom) = track.start_zoom {
+        if start_zoom <= 0.0 {
+            return Err(TrackReadError::InvalidData {
+                name: "start zoom".to_string(),
+                value: start_zoom.to_string(),
+            });
+        }
+
+        parsed_track.start_zoom = Some(start_zoom);
+    }
+
+    if let Some(zero_start) = track.zero_start {
+        parsed_track.zero_start = zero_start;
+    }
+    if let Some(frictionless) = track.frictionless {
+        parsed_track.frictionless = frictionless;
+    }
+
     Ok(parsed_track)
 }
+
+/// Converts the red, green, and blue properties of an LRA trigger into a color
+fn parse_trigger_color(
+    channels: [&Option<FaultyU32>; 3],
+    name: &str,
+) -> Result<RGBColor, TrackReadError> {
+    let mut parsed = [0u8; 3];
+
+    for (parsed_channel, channel) in parsed.iter_mut().zip(channels) {
+        *parsed_channel = match channel {
+            Some(FaultyU32::Valid(value)) => u8::try_from(*value).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| TrackReadError::InvalidData {
+            name: name.to_string(),
+            value: format!("{:?}", channel),
+        })?;
+    }
+
+    Ok(RGBColor {
+        red: parsed[0],
+        green: parsed[1],
+        blue: parsed[2],
+    })
+}
+
+/// Converts the separate red, green, and blue track properties into a color, which must either all be present or all be missing
+fn parse_color_properties(
+    channels: [Option<u32>; 3],
+    name: &str,
+) -> Result<Option<RGBColor>, TrackReadError> {
+    let invalid_color = || TrackReadError::InvalidData {
+        name: name.to_string(),
+        value: format!("{:?}", channels),
+    };
+
+    match channels {
+        [Some(red), Some(green), Some(blue)] => Ok(Some(RGBColor {
+            red: u8::try_from(red).map_err(|_| invalid_color())?,
+            green: u8::try_from(green).map_err(|_| invalid_color())?,
+            blue: u8::try_from(blue).map_err(|_| invalid_color())?,
+        })),
+        [None, None, None] => Ok(None),
+        _ => Err(invalid_color()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::trackjson::write;
+    use serde_json::{Value, json};
+
+    /// Reads a minimal track with the given properties added
+    fn read_with(properties: Value) -> Result<InternalTrackFormat, TrackReadError> {
+        let mut track = json!({
+            "label": "Test",
+            "version": "6.2",
+            "startPosition": { "x": 0.0, "y": 0.0 },
+        });
+        if let (Some(track), Value::Object(properties)) = (track.as_object_mut(), properties) {
+            track.extend(properties);
+        }
+
+        read(&track.to_string())
+    }
+
+    fn round_trip(track: &InternalTrackFormat) -> (InternalTrackFormat, Value) {
+        let json_string = write(track).unwrap();
+
+        (
+            read(&json_string).unwrap(),
+            serde_json::from_str(&json_string).unwrap(),
+        )
+    }
+
+    #[test]
+    fn time_based_triggers_round_trip() {
+        let track = read_with(json!({
+            "gameTriggers": [
+                {
+                    "triggerType": 0, "start": 0, "end": 10, "zoomTarget": 2.0,
+                    "backgroundRed": -999, "backgroundGreen": -999, "backgroundBlue": -999,
+                    "lineRed": -999, "lineGreen": -999, "lineBlue": -999,
+                },
+                {
+                    "triggerType": 1, "start": 10, "end": 20, "zoomTarget": -999,
+                    "backgroundRed": 10, "backgroundGreen": 20, "backgroundBlue": 30,
+                    "lineRed": -999, "lineGreen": -999, "lineBlue": -999,
+                },
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(track.triggers.len(), 2);
+        assert_eq!(track.triggers[0].event, TriggerEvent::Zoom { target: 2.0 });
+        assert_eq!(
+            track.triggers[1].event,
+            TriggerEvent::BackgroundColor(RGBColor {
+                red: 10,
+                green: 20,
+                blue: 30,
+            })
+        );
+
+        let (read_back, json) = round_trip(&track);
+        assert_eq!(read_back.triggers, track.triggers);
+        assert_eq!(json["gameTriggers"][0]["backgroundRed"], -999);
+        assert_eq!(json["gameTriggers"][1]["lineBlue"], -999);
+    }
+
+    #[test]
+    fn rejects_unused_channels_on_color_triggers() {
+        let result = read_with(json!({
+            "gameTriggers": [{
+                "triggerType": 2, "start": 0, "end": 10, "zoomTarget": -999,
+                "backgroundRed": -999, "backgroundGreen": -999, "backgroundBlue": -999,
+                "lineRed": 255, "lineGreen": -999, "lineBlue": 0,
+            }],
+        }));
+
+        assert!(matches!(result, Err(TrackReadError::InvalidData { .. })));
+    }
+
+    #[test]
+    fn line_based_triggers_round_trip() {
+        let track = read_with(json!({
+            "lines": [{
+                "id": 4, "type": 0, "x1": 0.0, "y1": 0.0, "x2": 10.0, "y2": 0.0,
+                "flipped": false, "leftExtended": false, "rightExtended": false,
+            }],
+            "triggers": [{ "ID": 4, "zoom": true, "target": 3.0, "frames": 40 }],
+        }))
+        .unwrap();
+
+        let expected = Some(LineTrigger {
+            zoom_target: 3.0,
+            frames: 40,
+        });
+        assert_eq!(track.simulation_lines[0].line_trigger, expected);
+
+        let (read_back, _) = round_trip(&track);
+        assert_eq!(read_back.simulation_lines[0].line_trigger, expected);
+    }
+
+    #[test]
+    fn drops_line_triggers_of_missing_lines() {
+        let track = read_with(json!({
+            "triggers": [{ "ID": 4, "zoom": true, "target": 3.0, "frames": 40 }],
+        }))
+        .unwrap();
+
+        assert!(track.simulation_lines.is_empty());
+        assert_eq!(round_trip(&track).1.get("triggers"), None);
+    }
+
+    #[test]
+    fn riders_round_trip() {
+        let track = read_with(json!({
+            "riders": [
+                {
+                    "startPosition": { "x": 5.0, "y": -5.0 },
+                    "startVelocity": { "x": 0.4, "y": 0.0 },
+                    "startAngle": 90.0,
+                    "remountable": 1,
+                },
+                {
+                    "startPosition": { "x": 20.0, "y": 0.0 },
+                    "startVelocity": { "x": 0.0, "y": 0.0 },
+                },
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(track.riders.len(), 2);
+        assert_eq!(track.start_position.x, 5.0);
+        assert_eq!(track.riders[0].start_angle, Some(90.0));
+        assert_eq!(track.riders[0].remountable, Some(true));
+        assert_eq!(track.riders[1].remountable, None);
+
+        let (read_back, _) = round_trip(&track);
+        assert_eq!(read_back.riders.len(), 2);
+        assert_eq!(read_back.riders[0].start_velocity.x, 0.4);
+        assert_eq!(read_back.riders[1].start_position.x, 20.0);
+    }
+
+    #[test]
+    fn writes_a_rider_for_the_start_position() {
+        let mut track = InternalTrackFormat::new();
+        track.start_position = Vec2 { x: 3.0, y: 4.0 };
+
+        let (read_back, json) = round_trip(&track);
+
+        assert_eq!(json["riders"].as_array().map(Vec::len), Some(1));
+        assert_eq!(read_back.riders[0].start_position.y, 4.0);
+        assert_eq!(read_back.start_position.x, 3.0);
+        assert_eq!(
+            json["riders"][0]["startVelocity"],
+            json!({ "x": 0.4, "y": 0.0 })
+        );
+
+        track.zero_start = true;
+        let (_, json) = round_trip(&track);
+
+        assert_eq!(
+            json["riders"][0]["startVelocity"],
+            json!({ "x": 0.0, "y": 0.0 })
+        );
+    }
+
+    #[test]
+    fn layers_and_folders_keep_their_order() {
+        let track = read_with(json!({
+            "layers": [
+                { "id": 0, "name": "Base", "visible": true, "editable": true, "folderId": -1 },
+                { "id": 1, "type": 1, "name": "Folder", "visible": true, "editable": true, "size": 1 },
+                { "id": 2, "type": 0, "name": "Inside", "visible": false, "editable": true, "folderId": 1 },
+                { "id": 3, "type": 1, "name": "Empty", "visible": true, "editable": false, "size": 0 },
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(track.layers.len(), 2);
+        assert_eq!(track.layers[0].folder_id, None);
+        assert_eq!(track.layers[1].folder_id, Some(1));
+        assert_eq!(track.layer_folders.len(), 2);
+
+        let (read_back, json) = round_trip(&track);
+        let written_ids: Vec<&Value> = json["layers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|layer| &layer["id"])
+            .collect();
+        assert_eq!(written_ids, [0, 1, 2, 3]);
+        assert_eq!(read_back.layers[1].name, "Inside");
+        assert_eq!(read_back.layer_folders[1].name, "Empty");
+    }
+
+    #[test]
+    fn physics_settings_round_trip() {
+        let track = read_with(json!({
+            "xGravity": 0.5,
+            "yGravity": -1.0,
+            "gravityWellSize": 5.0,
+        }))
+        .unwrap();
+
+        assert_eq!(track.physics.x_gravity, 0.5);
+        assert_eq!(track.physics.y_gravity, -1.0);
+        assert_eq!(track.physics.gravity_well_size, 5.0);
+        assert_eq!(round_trip(&track).0.physics, track.physics);
+    }
+
+    #[test]
+    fn colors_round_trip_and_are_range_checked() {
+        let track = read_with(json!({
+            "bgR": 10, "bgG": 20, "bgB": 30,
+            "lineR": 255, "lineG": 0, "lineB": 128,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            track.background_color,
+            Some(RGBColor {
+                red: 10,
+                green: 20,
+                blue: 30,
+            })
+        );
+        let read_back = round_trip(&track).0;
+        assert_eq!(read_back.background_color, track.background_color);
+        assert_eq!(read_back.line_color, track.line_color);
+
+        assert!(read_with(json!({ "bgR": 256, "bgG": 0, "bgB": 0 })).is_err());
+        assert!(read_with(json!({ "lineR": 0, "lineG": 0 })).is_err());
+    }
+
+    #[test]
+    fn start_zoom_round_trips_and_must_be_positive() {
+        let track = read_with(json!({ "startZoom": 4.0 })).unwrap();
+
+        assert_eq!(track.start_zoom, Some(4.0));
+        assert_eq!(round_trip(&track).0.start_zoom, Some(4.0));
+        assert_eq!(round_trip(&InternalTrackFormat::new()).0.start_zoom, None);
+        assert!(read_with(json!({ "startZoom": 0.0 })).is_err());
+    }
+
+    #[test]
+    fn zero_start_round_trips() {
+        let track = read_with(json!({ "zeroStart": true })).unwrap();
+
+        assert!(track.zero_start);
+        assert!(round_trip(&track).0.zero_start);
+        assert!(!round_trip(&InternalTrackFormat::new()).0.zero_start);
+    }
+
+    #[test]
+    fn rider_flags_round_trip() {
+        let mut track = read_with(json!({
+            "zeroStart": true,
+            "frictionless": true,
+            "riders": [{
+                "startPosition": { "x": 0.0, "y": 0.0 },
+                "startVelocity": { "x": 0.4, "y": 0.0 },
+                "remountable": true,
+            }],
+        }))
+        .unwrap();
+
+        assert!(track.zero_start && track.frictionless && track.remount);
+        let read_back = round_trip(&track).0;
+        assert!(read_back.zero_start && read_back.frictionless && read_back.remount);
+
+        // Riders without their own setting follow the track's remount setting
+        track.riders[0].remountable = None;
+        let (read_back, json) = round_trip(&track);
+        assert_eq!(json["riders"][0]["remountable"], true);
+        assert!(read_back.remount);
+    }
+
+    #[test]
+    fn unknown_fields_round_trip() {
+        let track = read_with(json!({
+            "customTrack": { "nested": [1, 2] },
+            "lines": [{
+                "id": 1, "type": 2, "x1": 0.0, "y1": 0.0, "x2": 10.0, "y2": 0.0,
+                "customLine": "kept",
+            }],
+            "riders": [{
+                "startPosition": { "x": 0.0, "y": 0.0 },
+                "startVelocity": { "x": 0.0, "y": 0.0 },
+                "customRider": 7,
+            }],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            track.json_extensions["customTrack"],
+            json!({ "nested": [1, 2] })
+        );
+
+        let json = round_trip(&track).1;
+        assert_eq!(json["customTrack"]["nested"][1], 2);
+        assert_eq!(json["lines"][0]["customLine"], "kept");
+        assert_eq!(json["riders"][0]["customRider"], 7);
+    }
+}
@@ -95,16 +95,13 @@ impl<'de> Visitor<'de> for LRAJsonArrayLineVisitor {
                     .ok_or_else(|| DeError::invalid_length(7, &self))?;
 
                 let mut multiplier: u32 = 1;
-                match seq.next_element::<serde::de::IgnoredAny>()? {
-                    Some(_) => {
-                        let _: serde::de::IgnoredAny = seq
-                            .next_element()?
-                            .ok_or_else(|| DeError::invalid_length(9, &self))?;
-                        multiplier = seq
-                            .next_element()?
-                            .ok_or_else(|| DeError::invalid_length(10, &self))?;
-                    }
-                    None => {}
+                if seq.next_element::<serde::de::IgnoredAny>()?.is_some() {
+                    let _: serde::de::IgnoredAny = seq
+                        .next_element()?
+                        .ok_or_else(|| DeError::invalid_length(9, &self))?;
+                    multiplier = seq
+                        .next_element()?
+                        .ok_or_else(|| DeError::invalid_length(10, &self))?;
                 }
 
                 Ok(LRAJsonArrayLine::RedLine(
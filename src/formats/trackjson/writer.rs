@@ -1,8 +1,15 @@
-use super::{JsonLayer, JsonLine, JsonRider, JsonTrack, Vec2};
+use super::{
+    FaultyU32, JsonLayer, JsonLine, JsonRider, JsonTrack, LRA_TRIGGER_UNUSED,
+    LRAJsonLegacyZoomTrigger, LRAJsonTrigger, Vec2,
+};
 use crate::{
     TrackWriteError,
-    formats::internal::{GridVersion, InternalTrackFormat, LineType},
+    formats::internal::{
+        GridVersion, InternalTrackFormat, JsonExtensions, LayerFolder, LineType, RGBColor,
+        TriggerEvent,
+    },
 };
+use std::collections::HashSet;
 
 pub fn write(internal: &InternalTrackFormat) -> Result<String, TrackWriteError> {
     let version = match internal.grid_version {
@@ -12,6 +19,7 @@ pub fn write(internal: &InternalTrackFormat) -> Result<String, TrackWriteError>
     };
 
     let mut lines = Vec::<JsonLine>::new();
+    let mut line_based_triggers = Vec::<LRAJsonLegacyZoomTrigger>::new();
 
     for line in &internal.simulation_lines {
         let line_type = if line.base_line.line_type == LineType::BLUE {
@@ -33,7 +41,18 @@ pub fn write(internal: &InternalTrackFormat) -> Result<String, TrackWriteError>
             extended: None,
             multiplier: line.multiplier,
             width: None,
+            layer: line.base_line.layer,
+            extensions: line.base_line.json_extensions.clone(),
         });
+
+        if let Some(line_trigger) = &line.line_trigger {
+            line_based_triggers.push(LRAJsonLegacyZoomTrigger {
+                id: line.base_line.id,
+                zoom: true,
+                target: line_trigger.zoom_target,
+                frames: line_trigger.frames,
+            });
+        }
     }
 
     for line in &internal.scenery_lines {
@@ -50,6 +69,8 @@ pub fn write(internal: &InternalTrackFormat) -> Result<String, TrackWriteError>
             extended: None,
             multiplier: None,
             width: line.width,
+            layer: line.base_line.layer,
+            extensions: line.base_line.json_extensions.clone(),
         });
     }
 
@@ -58,6 +79,113 @@ pub fn write(internal: &InternalTrackFormat) -> Result<String, TrackWriteError>
         y: internal.start_position.y,
     };
 
+    let mut layers = Vec::<JsonLayer>::new();
+    let mut written_folders = HashSet::<u32>::new();
+
+    for layer in &internal.layers {
+        // Folders are written directly before the first layer they contain
+        let folder = layer.folder_id.and_then(|folder_id| {
+            internal
+                .layer_folders
+                .iter()
+                .find(|folder| folder.id == folder_id)
+        });
+
+        if let Some(folder) = folder
+            && written_folders.insert(folder.id)
+        {
+            layers.push(folder_to_json(folder));
+        }
+
+        layers.push(JsonLayer {
+            id: layer.id,
+            layer_type: Some(0),
+            name: layer.name.clone(),
+            visible: layer.visible,
+            editable: layer.editable,
+            folder_id: layer.folder_id.map(FaultyU32::Valid),
+            size: None,
+        });
+    }
+
+    for folder in &internal.layer_folders {
+        if written_folders.insert(folder.id) {
+            layers.push(folder_to_json(folder));
+        }
+    }
+
+    let mut riders = Vec::<JsonRider>::new();
+
+    for rider in &internal.riders {
+        riders.push(JsonRider {
+            start_pos: Vec2 {
+                x: rider.start_position.x,
+                y: rider.start_position.y,
+            },
+            start_vel: Vec2 {
+                x: rider.start_velocity.x,
+                y: rider.start_velocity.y,
+            },
+            angle: rider.start_angle,
+            remountable: rider
+                .remountable
+                .or_else(|| internal.remount.then_some(true)),
+            extensions: rider.json_extensions.clone(),
+        });
+    }
+
+    // Tracks without explicit riders still start with one rider at the start position,
+    // moving with the default start velocity unless the track uses zero start
+    if riders.is_empty() {
+        riders.push(JsonRider {
+            start_pos: start_pos.clone(),
+            start_vel: Vec2 {
+                x: if internal.zero_start { 0.0 } else { 0.4 },
+                y: 0.0,
+            },
+            angle: None,
+            remountable: internal.remount.then_some(true),
+            extensions: JsonExtensions::new(),
+        });
+    }
+
+    let mut time_based_triggers = Vec::<LRAJsonTrigger>::new();
+
+    for trigger in &internal.triggers {
+        let mut json_trigger = LRAJsonTrigger {
+            trigger_type: 0,
+            start: trigger.start_frame,
+            end: trigger.end_frame,
+            zoom_target: LRA_TRIGGER_UNUSED as f32,
+            background_red: Some(FaultyU32::Invalid(LRA_TRIGGER_UNUSED)),
+            background_green: Some(FaultyU32::Invalid(LRA_TRIGGER_UNUSED)),
+            background_blue: Some(FaultyU32::Invalid(LRA_TRIGGER_UNUSED)),
+            line_red: Some(FaultyU32::Invalid(LRA_TRIGGER_UNUSED)),
+            line_green: Some(FaultyU32::Invalid(LRA_TRIGGER_UNUSED)),
+            line_blue: Some(FaultyU32::Invalid(LRA_TRIGGER_UNUSED)),
+        };
+
+        match trigger.event {
+            TriggerEvent::Zoom { target } => {
+                json_trigger.zoom_target = target;
+            }
+            TriggerEvent::BackgroundColor(RGBColor { red, green, blue }) => {
+                json_trigger.trigger_type = 1;
+                json_trigger.background_red = Some(FaultyU32::Valid(red.into()));
+                json_trigger.background_green = Some(FaultyU32::Valid(green.into()));
+                json_trigger.background_blue = Some(FaultyU32::Valid(blue.into()));
+            }
+            TriggerEvent::LineColor(RGBColor { red, green, blue }) => {
+                json_trigger.trigger_type = 2;
+                json_trigger.line_red = Some(FaultyU32::Valid(red.into()));
+                json_trigger.line_green = Some(FaultyU32::Valid(green.into()));
+                json_trigger.line_blue = Some(FaultyU32::Valid(blue.into()));
+            }
+        }
+
+        time_based_triggers.push(json_trigger);
+    }
+
     let track = JsonTrack {
         label: internal.title.clone(),
         version,
@@ -67,23 +195,32 @@ pub fn write(internal: &InternalTrackFormat) -> Result<String, TrackWriteError>
         description: Some(internal.description.clone()),
         duration: Some(internal.duration),
         script: Some(internal.script.clone()),
-        layers: Some(Vec::<JsonLayer>::new()),
-        riders: Some(Vec::<JsonRider>::new()),
+        layers: Some(layers),
+        riders: Some(riders),
         line_array: None,
-        time_based_triggers: None,
-        // TODO
-        start_zoom: None,
-        zero_start: None,
-        line_based_triggers: None,
-        line_color_blue: None,
-        line_color_green: None,
-        line_color_red: None,
-        background_color_blue: None,
-        background_color_green: None,
-        background_color_red: None,
-        gravity_well_size: None,
-        x_gravity: None,
-        y_gravity: None,
+        time_based_triggers: if time_based_triggers.is_empty() {
+            None
+        } else {
+            Some(time_based_triggers)
+        },
+        line_based_triggers: if line_based_triggers.is_empty() {
+            None
+        } else {
+            Some(line_based_triggers)
+        },
+        x_gravity: Some(internal.physics.x_gravity),
+        y_gravity: Some(internal.physics.y_gravity),
+        gravity_well_size: Some(internal.physics.gravity_well_size),
+        background_color_red: internal.background_color.map(|color| color.red.into()),
+        background_color_green: internal.background_color.map(|color| color.green.into()),
+        background_color_blue: internal.background_color.map(|color| color.blue.into()),
+        line_color_red: internal.line_color.map(|color| color.red.into()),
+        line_color_green: internal.line_color.map(|color| color.green.into()),
+        line_color_blue: internal.line_color.map(|color| color.blue.into()),
+        start_zoom: internal.start_zoom,
+        zero_start: Some(internal.zero_start),
+        frictionless: Some(internal.frictionless),
+        extensions: internal.json_extensions.clone(),
     };
 
     let track_string = serde_json::to_string(&track).map_err(|err| TrackWriteError::Other {
@@ -92,3 +229,15 @@ pub fn write(internal: &InternalTrackFormat) -> Result<String, TrackWriteError>
 
     Ok(track_string)
 }
+
+fn folder_to_json(folder: &LayerFolder) -> JsonLayer {
+    JsonLayer {
+        id: folder.id,
+        layer_type: Some(1),
+        name: folder.name.clone(),
+        visible: folder.visible,
+        editable: folder.editable,
+        folder_id: None,
+        size: Some(folder.size),
+    }
+}
@@ -8,7 +8,10 @@ use byteorder::{LittleEndian, ReadBytesExt};
 use crate::{
     TrackReadError,
     formats::{
-        internal::{GridVersion, InternalTrackFormat, Line, LineType, SceneryLine, SimulationLine},
+        internal::{
+            GridVersion, InternalTrackFormat, JsonExtensions, Line, LineIdAllocator, LineTrigger,
+            LineType, RGBColor, SceneryLine, SimulationLine, SongInfo, Trigger, TriggerEvent,
+        },
         trk::{
             FEATURE_BACKGROUND_COLOR_B, FEATURE_BACKGROUND_COLOR_G, FEATURE_BACKGROUND_COLOR_R,
             FEATURE_GRAVITY_WELL_SIZE, FEATURE_LINE_COLOR_B, FEATURE_LINE_COLOR_G,
@@ -20,8 +23,9 @@ use crate::{
 };
 
 use super::{
-    FEATURE_6_1, FEATURE_IGNORABLE_TRIGGER, FEATURE_LINE_COLOR_R, FEATURE_RED_MULTIPLIER,
-    FEATURE_SCENERY_WIDTH, FEATURE_SONG_INFO,
+    FEATURE_6_1, FEATURE_FRICTIONLESS, FEATURE_IGNORABLE_TRIGGER, FEATURE_LINE_COLOR_R,
+    FEATURE_RED_MULTIPLIER, FEATURE_REMOUNT, FEATURE_SCENERY_WIDTH, FEATURE_SONG_INFO,
+    FEATURE_ZERO_START,
 };
 
 pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
@@ -32,7 +36,7 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
     let mut magic_number = [0u8; 4];
     cursor.read_exact(&mut magic_number)?;
 
-    if &magic_number != &[b'T', b'R', b'K', 0xF2] {
+    if magic_number != [b'T', b'R', b'K', 0xF2] {
         return Err(TrackReadError::InvalidData {
             name: "magic number".to_string(),
             value: bytes_to_hex_string(&magic_number),
@@ -69,6 +73,10 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
         GridVersion::V6_2
     };
 
+    parsed_track.zero_start = included_features.contains(FEATURE_ZERO_START);
+    parsed_track.remount = included_features.contains(FEATURE_REMOUNT);
+    parsed_track.frictionless = included_features.contains(FEATURE_FRICTIONLESS);
+
     if included_features.contains(FEATURE_SONG_INFO) {
         let mut song_string_length = 0;
         let mut bit_shift = 0;
@@ -99,11 +107,10 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
             });
         }
 
-        // TODO: Unused
-        #[allow(unused_variables)]
-        let name = song_data[0];
-        #[allow(unused_variables)]
-        let seconds_offset = song_data[1].parse::<f64>()?;
+        parsed_track.song_info = Some(SongInfo {
+            name: song_data[0].to_string(),
+            offset: song_data[1].parse::<f64>()?,
+        });
     }
 
     parsed_track.start_position.x = cursor.read_f64::<LittleEndian>()?;
@@ -111,8 +118,6 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
 
     let line_count = cursor.read_u32::<LittleEndian>()?;
 
-    let mut max_id = 0;
-
     for _ in 0..line_count {
         let mut line_id: u32 = 0;
         let flags = cursor.read_u8()?;
@@ -135,9 +140,9 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
         let mut line_multiplier: Option<f64> = None;
         let mut line_scenery_width: Option<f64> = None;
 
-        // TODO: Unused
-        let mut _line_zoom_target: Option<f32> = None;
-        let mut _line_zoom_frames: Option<i16> = None;
+        let mut line_trigger: Option<LineTrigger> = None;
+        let mut previous_line_id: Option<u32> = None;
+        let mut next_line_id: Option<u32> = None;
 
         if line_type == LineType::RED && included_features.contains(FEATURE_RED_MULTIPLIER) {
             line_multiplier = Some(cursor.read_u8()? as f64);
@@ -151,17 +156,24 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
             if included_features.contains(FEATURE_IGNORABLE_TRIGGER) {
                 let has_zoom_trigger = cursor.read_u8()?;
                 if has_zoom_trigger == 1 {
-                    _line_zoom_target = Some(cursor.read_f32::<LittleEndian>()?);
-                    _line_zoom_frames = Some(cursor.read_i16::<LittleEndian>()?);
+                    let zoom_target = cursor.read_f32::<LittleEndian>()?;
+                    let frames = cursor.read_i16::<LittleEndian>()?;
+                    line_trigger = Some(LineTrigger {
+                        zoom_target,
+                        frames: u32::try_from(frames).map_err(|_| TrackReadError::InvalidData {
+                            name: "line trigger frames".to_string(),
+                            value: frames.to_string(),
+                        })?,
+                    });
                 }
             }
 
             line_id = cursor.read_u32::<LittleEndian>()?;
-            max_id = max_id.max(line_id);
 
             if line_ext != 0 {
-                _ = cursor.read_i32::<LittleEndian>()?; // Prev line id or -1
-                _ = cursor.read_i32::<LittleEndian>()?; // Next line id or -1
+                // Prev/next line id, or -1 if there is none
+                previous_line_id = u32::try_from(cursor.read_i32::<LittleEndian>()?).ok();
+                next_line_id = u32::try_from(cursor.read_i32::<LittleEndian>()?).ok();
             }
         }
 
@@ -177,6 +189,8 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
             x2: line_x2,
             y2: line_y2,
             line_type,
+            layer: None,
+            json_extensions: JsonExtensions::new(),
         };
 
         if line_type == LineType::GREEN {
@@ -191,21 +205,29 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
                 left_extension: line_ext & 0x1 != 0,
                 right_extension: line_ext & 0x2 != 0,
                 multiplier: line_multiplier,
+                line_trigger,
+                previous_line_id,
+                next_line_id,
             });
         }
     }
 
-    for line in parsed_track.scenery_lines.iter_mut() {
-        max_id += 1;
-        line.base_line.id = max_id;
+    // Scenery lines don't store ids in TRK, so give them ids that aren't used yet, starting at 1
+    // like the ids this reader has always assigned
+    let scenery_lines = std::mem::take(&mut parsed_track.scenery_lines);
+    let mut line_ids = LineIdAllocator::starting_at(&parsed_track, 1);
+
+    for mut line in scenery_lines {
+        line.base_line.id = line_ids.allocate().map_err(|err| TrackReadError::Other {
+            message: format!("Failed to assign scenery line ids: {}", err),
+        })?;
+        parsed_track.scenery_lines.push(line);
     }
 
     let current = cursor.stream_position()?;
     let end = cursor.seek(SeekFrom::End(0))?;
     cursor.seek(SeekFrom::Start(current))?;
 
-    // TODO: REMOUNT, ZEROSTART, FRICTIONLESS
-
     if current == end {
         return Ok(parsed_track);
     }
@@ -223,6 +245,8 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
     }
 
     let num_entries = cursor.read_u16::<LittleEndian>()?;
+    let mut background_channels: [Option<u8>; 3] = [None; 3];
+    let mut line_channels: [Option<u8>; 3] = [None; 3];
 
     for _ in 0..num_entries {
         let meta_string = parse_string::<LittleEndian>(&mut cursor, StringLength::U16)?;
@@ -238,102 +262,104 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
         let key = key_value_pair[0];
         let value = key_value_pair[1];
 
-        // TODO: Unused
         match key {
             FEATURE_START_ZOOM => {
-                #[allow(unused_variables)]
                 let start_zoom = value.parse::<f32>()?;
+
+                if start_zoom <= 0.0 {
+                    return Err(TrackReadError::InvalidData {
+                        name: "start zoom".to_string(),
+                        value: start_zoom.to_string(),
+                    });
+                }
+
+                parsed_track.start_zoom = Some(start_zoom);
             }
             FEATURE_X_GRAVITY => {
-                #[allow(unused_variables)]
-                let x_gravity = value.parse::<f32>()?;
+                parsed_track.physics.x_gravity = value.parse::<f32>()?;
             }
             FEATURE_Y_GRAVITY => {
-                #[allow(unused_variables)]
-                let y_gravity = value.parse::<f32>()?;
+                parsed_track.physics.y_gravity = value.parse::<f32>()?;
             }
             FEATURE_GRAVITY_WELL_SIZE => {
-                #[allow(unused_variables)]
-                let gravity_well_size = value.parse::<f64>()?;
+                parsed_track.physics.gravity_well_size = value.parse::<f64>()?;
             }
             FEATURE_BACKGROUND_COLOR_R => {
-                #[allow(unused_variables)]
-                let background_color_red = value.parse::<i32>()?;
+                background_channels[0] = Some(parse_color_channel(value, "background color")?);
             }
             FEATURE_BACKGROUND_COLOR_G => {
-                #[allow(unused_variables)]
-                let background_color_green = value.parse::<i32>()?;
+                background_channels[1] = Some(parse_color_channel(value, "background color")?);
             }
             FEATURE_BACKGROUND_COLOR_B => {
-                #[allow(unused_variables)]
-                let background_color_blue = value.parse::<i32>()?;
+                background_channels[2] = Some(parse_color_channel(value, "background color")?);
             }
             FEATURE_LINE_COLOR_R => {
-                #[allow(unused_variables)]
-                let line_color_red = value.parse::<i32>()?;
+                line_channels[0] = Some(parse_color_channel(value, "line color")?);
             }
             FEATURE_LINE_COLOR_G => {
-                #[allow(unused_variables)]
-                let line_color_green = value.parse::<i32>()?;
+                line_channels[1] = Some(parse_color_channel(value, "line color")?);
             }
             FEATURE_LINE_COLOR_B => {
-                #[allow(unused_variables)]
-                let line_color_blue = value.parse::<i32>()?;
+                line_channels[2] = Some(parse_color_channel(value, "line color")?);
             }
             FEATURE_TRIGGERS => {
                 for (i, trigger) in value.split('&').filter(|s| !s.is_empty()).enumerate() {
                     let values: Vec<&str> = trigger.split(':').filter(|s| !s.is_empty()).collect();
 
-                    if values.len() < 1 {
+                    if values.is_empty() {
                         return Err(TrackReadError::InvalidData {
                             name: "size of trigger data".to_string(),
                             value: "0".to_string(),
                         });
                     }
 
-                    match values[0] {
+                    let expected_len = match values[0] {
+                        "0" => 4,
+                        "1" | "2" => 6,
+                        other => {
+                            return Err(TrackReadError::InvalidData {
+                                name: format!("triggers {} type", i),
+                                value: other.to_string(),
+                            });
+                        }
+                    };
+
+                    if values.len() < expected_len {
+                        return Err(TrackReadError::InvalidData {
+                            name: "size of trigger data".to_string(),
+                            value: values.len().to_string(),
+                        });
+                    }
+
+                    let (event, start_frame, end_frame) = match values[0] {
                         "0" => {
                             // Zoom
-                            #[allow(unused_variables)]
-                            let target_zoom = values[1].parse::<f32>()?;
-                            #[allow(unused_variables)]
-                            let start_frame = values[2].parse::<i32>()?;
-                            #[allow(unused_variables)]
-                            let end_frame = values[3].parse::<i32>()?;
+                            let target = values[1].parse::<f32>()?;
+                            let start_frame = values[2].parse::<u32>()?;
+                            let end_frame = values[3].parse::<u32>()?;
+                            (TriggerEvent::Zoom { target }, start_frame, end_frame)
                         }
                         "1" => {
                             // Background Color
-                            #[allow(unused_variables)]
-                            let target_bg_red = values[1].parse::<i32>()?;
-                            #[allow(unused_variables)]
-                            let target_bg_green = values[2].parse::<i32>()?;
-                            #[allow(unused_variables)]
-                            let target_bg_blue = values[3].parse::<i32>()?;
-                            #[allow(unused_variables)]
-                            let start_frame = values[4].parse::<i32>()?;
-                            #[allow(unused_variables)]
-                            let end_frame = values[5].parse::<i32>()?;
+                            let color = parse_color(&values[1..4], "trigger background color")?;
+                            let start_frame = values[4].parse::<u32>()?;
+                            let end_frame = values[5].parse::<u32>()?;
+                            (TriggerEvent::BackgroundColor(color), start_frame, end_frame)
                         }
-                        "2" => {
+                        _ => {
                             // Line Color
-                            #[allow(unused_variables)]
-                            let target_line_red = values[1].parse::<i32>()?;
-                            #[allow(unused_variables)]
-                            let target_line_green = values[2].parse::<i32>()?;
-                            #[allow(unused_variables)]
-                            let target_line_blue = values[3].parse::<i32>()?;
-                            #[allow(unused_variables)]
-                            let start_frame = values[4].parse::<i32>()?;
-                            #[allow(unused_variables)]
-                            let end_frame = values[5].parse::<i32>()?;
-                        }
-                        other => {
-                            return Err(TrackReadError::InvalidData {
-                                name: format!("triggers {} type", i),
-                                value: other.to_string(),
-                            });
+                            let color = parse_color(&values[1..4], "trigger line color")?;
+                            let start_frame = values[4].parse::<u32>()?;
+                            let end_frame = values[5].parse::<u32>()?;
+                            (TriggerEvent::LineColor(color), start_frame, end_frame)
                         }
-                    }
+                    };
+
+                    parsed_track.triggers.push(Trigger {
+                        start_frame,
+                        end_frame,
+                        event,
+                    });
                 }
             }
             other => {
@@ -345,7 +371,252 @@ pub fn read(data: &[u8]) -> Result<InternalTrackFormat, TrackReadError> {
         }
     }
 
-    // TODO: STARTZOOM, XGRAVITY, YGRAVITY, GRAVITYWELLSIZE, BGCOLORR/G/B, LINECOLORR/G/B, TRIGGERS
+    parsed_track.background_color =
+        combine_color_channels(background_channels, "background color")?;
+    parsed_track.line_color = combine_color_channels(line_channels, "line color")?;
 
     Ok(parsed_track)
 }
+
+/// Parses a color channel string, rejecting values outside of the u8 range
+fn parse_color_channel(channel: &str, name: &str) -> Result<u8, TrackReadError> {
+    let value = channel.parse::<i32>()?;
+
+    u8::try_from(value).map_err(|_| TrackReadError::InvalidData {
+        name: name.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// Parses red, green, and blue strings into a color
+fn parse_color(channels: &[&str], name: &str) -> Result<RGBColor, TrackReadError> {
+    Ok(RGBColor {
+        red: parse_color_channel(channels[0], name)?,
+        green: parse_color_channel(channels[1], name)?,
+        blue: parse_color_channel(channels[2], name)?,
+    })
+}
+
+/// Combines color channels stored in separate metadata entries, which must either all be present or all be missing
+fn combine_color_channels(
+    channels: [Option<u8>; 3],
+    name: &str,
+) -> Result<Option<RGBColor>, TrackReadError> {
+    match channels {
+        [Some(red), Some(green), Some(blue)] => Ok(Some(RGBColor { red, green, blue })),
+        [None, None, None] => Ok(None),
+        partial => Err(TrackReadError::InvalidData {
+            name: name.to_string(),
+            value: format!("{:?}", partial),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::WriteBytesExt;
+
+    /// Builds a TRK file starting at (0, 0), where `prefix` is written before the start position
+    /// and each line already contains its encoded data
+    fn trk_track(features: &str, prefix: &[u8], lines: &[Vec<u8>], metadata: &[&str]) -> Vec<u8> {
+        let mut bytes = b"TRK\xF2\x01".to_vec();
+        bytes
+            .write_u16::<LittleEndian>(features.len() as u16)
+            .unwrap();
+        bytes.extend_from_slice(features.as_bytes());
+        bytes.extend_from_slice(prefix);
+        bytes.write_f64::<LittleEndian>(0.0).unwrap();
+        bytes.write_f64::<LittleEndian>(0.0).unwrap();
+        bytes.write_u32::<LittleEndian>(lines.len() as u32).unwrap();
+        for line in lines {
+            bytes.extend_from_slice(line);
+        }
+
+        if !metadata.is_empty() {
+            bytes.extend_from_slice(b"META");
+            bytes
+                .write_u16::<LittleEndian>(metadata.len() as u16)
+                .unwrap();
+            for entry in metadata {
+                bytes.write_u16::<LittleEndian>(entry.len() as u16).unwrap();
+                bytes.extend_from_slice(entry.as_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    /// Appends the coordinates of a line from (0, 0) to (10, 0) to its encoded flags and ids
+    fn line_bytes(mut line: Vec<u8>) -> Vec<u8> {
+        for coordinate in [0.0, 0.0, 10.0, 0.0] {
+            line.write_f64::<LittleEndian>(coordinate).unwrap();
+        }
+        line
+    }
+
+    #[test]
+    fn reads_metadata_triggers() {
+        let track = read(&trk_track(
+            "",
+            &[],
+            &[],
+            &["TRIGGERS=0:2.5:10:20&1:255:0:0:30:40&2:0:128:0:50:60"],
+        ))
+        .unwrap();
+
+        assert_eq!(track.triggers.len(), 3);
+        assert_eq!(track.triggers[0].event, TriggerEvent::Zoom { target: 2.5 });
+        assert_eq!(
+            (track.triggers[1].start_frame, track.triggers[1].end_frame),
+            (30, 40)
+        );
+        assert_eq!(
+            track.triggers[2].event,
+            TriggerEvent::LineColor(RGBColor {
+                red: 0,
+                green: 128,
+                blue: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn reads_line_triggers() {
+        let mut with_trigger = vec![1u8, 1];
+        with_trigger.write_f32::<LittleEndian>(2.0).unwrap();
+        with_trigger.write_i16::<LittleEndian>(40).unwrap();
+        with_trigger.write_u32::<LittleEndian>(1).unwrap();
+        let mut without_trigger = vec![1u8, 0];
+        without_trigger.write_u32::<LittleEndian>(2).unwrap();
+
+        let track = read(&trk_track(
+            "IGNORABLE_TRIGGER",
+            &[],
+            &[line_bytes(with_trigger), line_bytes(without_trigger)],
+            &[],
+        ))
+        .unwrap();
+
+        assert_eq!(
+            track.simulation_lines[0].line_trigger,
+            Some(LineTrigger {
+                zoom_target: 2.0,
+                frames: 40,
+            })
+        );
+        assert_eq!(track.simulation_lines[1].line_trigger, None);
+    }
+
+    #[test]
+    fn reads_physics_metadata() {
+        let track = read(&trk_track(
+            "",
+            &[],
+            &[],
+            &["XGRAVITY=0.5", "YGRAVITY=-2", "GRAVITYWELLSIZE=4.5"],
+        ))
+        .unwrap();
+
+        assert_eq!(track.physics.x_gravity, 0.5);
+        assert_eq!(track.physics.y_gravity, -2.0);
+        assert_eq!(track.physics.gravity_well_size, 4.5);
+    }
+
+    #[test]
+    fn range_checks_colors() {
+        let track = read(&trk_track(
+            "",
+            &[],
+            &[],
+            &["BGCOLORR=1", "BGCOLORG=2", "BGCOLORB=3"],
+        ))
+        .unwrap();
+        assert_eq!(
+            track.background_color,
+            Some(RGBColor {
+                red: 1,
+                green: 2,
+                blue: 3,
+            })
+        );
+        assert_eq!(track.line_color, None);
+
+        let out_of_range = ["LINECOLORR=300", "LINECOLORG=0", "LINECOLORB=0"];
+        assert!(read(&trk_track("", &[], &[], &out_of_range)).is_err());
+        assert!(read(&trk_track("", &[], &[], &["BGCOLORR=1"])).is_err());
+        assert!(read(&trk_track("", &[], &[], &["TRIGGERS=1:0:0:-1:0:10"])).is_err());
+    }
+
+    #[test]
+    fn start_zoom_must_be_positive() {
+        let track = read(&trk_track("", &[], &[], &["STARTZOOM=2.5"])).unwrap();
+
+        assert_eq!(track.start_zoom, Some(2.5));
+        assert!(read(&trk_track("", &[], &[], &["STARTZOOM=-1"])).is_err());
+    }
+
+    #[test]
+    fn reads_rider_flags() {
+        let track = read(&trk_track("ZEROSTART;FRICTIONLESS", &[], &[], &[])).unwrap();
+
+        assert!(track.zero_start);
+        assert!(!track.remount);
+        assert!(track.frictionless);
+    }
+
+    #[test]
+    fn reads_song_info() {
+        let song = "Song Name\r\n12.5";
+        let mut prefix = vec![song.len() as u8];
+        prefix.extend_from_slice(song.as_bytes());
+
+        let track = read(&trk_track("SONGINFO", &prefix, &[], &[])).unwrap();
+
+        assert_eq!(
+            track.song_info,
+            Some(SongInfo {
+                name: "Song Name".to_string(),
+                offset: 12.5,
+            })
+        );
+    }
+
+    #[test]
+    fn reads_line_links() {
+        // Blue line with both extensions, linked to line 2 at its start and nothing at its end
+        let mut linked = vec![1u8 | (3 << 5)];
+        linked.write_u32::<LittleEndian>(1).unwrap();
+        linked.write_i32::<LittleEndian>(2).unwrap();
+        linked.write_i32::<LittleEndian>(-1).unwrap();
+
+        let track = read(&trk_track("", &[], &[line_bytes(linked)], &[])).unwrap();
+
+        assert_eq!(track.simulation_lines[0].previous_line_id, Some(2));
+        assert_eq!(track.simulation_lines[0].next_line_id, None);
+    }
+
+    #[test]
+    fn numbers_scenery_lines_after_simulation_lines() {
+        let scenery = line_bytes(vec![0u8]);
+        let mut simulation = vec![1u8];
+        simulation.write_u32::<LittleEndian>(5).unwrap();
+
+        let scenery_only = read(&trk_track(
+            "",
+            &[],
+            &[scenery.clone(), scenery.clone()],
+            &[],
+        ))
+        .unwrap();
+        let scenery_ids: Vec<u32> = scenery_only
+            .scenery_lines
+            .iter()
+            .map(|line| line.base_line.id)
+            .collect();
+        assert_eq!(scenery_ids, vec![1, 2]);
+
+        let mixed = read(&trk_track("", &[], &[scenery, line_bytes(simulation)], &[])).unwrap();
+        assert_eq!(mixed.scenery_lines[0].base_line.id, 6);
+    }
+}
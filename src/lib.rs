@@ -2,7 +2,9 @@
 //! Supported formats: LRB (read/write), SOL (read/write), JSON (read/write), TRK (read)
 //!
 //! # Usage
-//! ```rust
+//! ```no_run
+//! use std::fs;
+//!
 //! let track_bytes = fs::read_to_string("test.track.json").unwrap();
 //! let track = lr_formatter_rs::trackjson::read(&track_bytes).unwrap();
 //! println!("Title: {}", track.title);
@@ -12,5 +14,5 @@ mod errors;
 mod formats;
 pub(crate) mod util;
 
-pub use errors::{TrackReadError, TrackWriteError};
+pub use errors::{TrackBuildError, TrackReadError, TrackWriteError};
 pub use formats::{internal, lrb, sol, trackjson, trk};
@@ -19,6 +19,7 @@ pub enum ParseLengthPrefixedStringError {
 pub(crate) enum StringLength {
     U8,
     U16,
+    U32,
     Fixed(usize),
 }
 
@@ -30,9 +31,16 @@ pub(crate) fn parse_string<B: ByteOrder>(
     let length = match length_type {
         StringLength::U8 => cursor.read_u8()? as usize,
         StringLength::U16 => cursor.read_u16::<B>()? as usize,
+        StringLength::U32 => cursor.read_u32::<B>()? as usize,
         StringLength::Fixed(size) => size,
     };
 
+    // The length comes from untrusted data, so check it before allocating the buffer
+    let remaining = (cursor.get_ref().len() as u64).saturating_sub(cursor.position());
+    if length as u64 > remaining {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
     let mut buffer = vec![0; length];
     cursor.read_exact(&mut buffer)?;
     let string = String::from_utf8(buffer)
@@ -40,3 +48,20 @@ pub(crate) fn parse_string<B: ByteOrder>(
 
     Ok(string)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::LittleEndian;
+
+    #[test]
+    fn rejects_lengths_past_the_end() {
+        let data = [0xFF, 0xFF, 0xFF, 0xFF, b'a'];
+        let result = parse_string::<LittleEndian>(&mut io::Cursor::new(&data), StringLength::U32);
+
+        assert!(matches!(
+            result,
+            Err(ParseLengthPrefixedStringError::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
+}